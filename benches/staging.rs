@@ -0,0 +1,88 @@
+use std::{hint::black_box, path::PathBuf};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use gitwatch_rs::{filter::PathFilter, repo::GitwatchRepo};
+use support::SyntheticRepo;
+
+mod support;
+
+const FILE_COUNTS: [usize; 2] = [10_000, 100_000];
+
+fn bench_staging(c: &mut Criterion) {
+    let repos = FILE_COUNTS
+        .iter()
+        .map(|file_count| SyntheticRepo::new(*file_count).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("is_path_ignored");
+    for synthetic_repo in &repos {
+        let filter = PathFilter::new(&synthetic_repo.path, None).unwrap();
+        let files = &synthetic_repo.files;
+        group.throughput(Throughput::Elements(files.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter(files.len()), |b| {
+            b.iter(|| {
+                files
+                    .iter()
+                    .filter(|path| filter.is_path_ignored(black_box(path)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+
+    // a full scan of 100k files takes about a second
+    let mut group = c.benchmark_group("stage_changes");
+    group.sample_size(10);
+    for synthetic_repo in &repos {
+        let file_count = synthetic_repo.files.len();
+        let repo = GitwatchRepo::new(&synthetic_repo.app_config()).unwrap();
+        group.bench_function(BenchmarkId::new("full_scan", file_count), |b| {
+            b.iter(|| repo.stage_paths(&[]).unwrap())
+        });
+        group.bench_function(BenchmarkId::new("changed_file", file_count), |b| {
+            b.iter_batched(
+                || vec![synthetic_repo.modify_file().unwrap()],
+                |paths| repo.stage_paths(&paths).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        // commit the staged changes, so the other benchmarks start from a clean tree
+        repo.process_changes(&[]).unwrap();
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("staged_changes");
+    for synthetic_repo in &repos {
+        let repo = GitwatchRepo::new(&synthetic_repo.app_config()).unwrap();
+        group.bench_function(
+            BenchmarkId::from_parameter(synthetic_repo.files.len()),
+            |b| b.iter(|| repo.staged_change_count().unwrap()),
+        );
+    }
+    group.finish();
+
+    // latency from a detected change to its commit
+    let mut group = c.benchmark_group("process_changes");
+    group.sample_size(10);
+    for synthetic_repo in &repos {
+        let repo = GitwatchRepo::new(&synthetic_repo.app_config()).unwrap();
+        group.bench_function(
+            BenchmarkId::from_parameter(synthetic_repo.files.len()),
+            |b| {
+                b.iter_batched(
+                    || vec![synthetic_repo.modify_file().unwrap()],
+                    |paths: Vec<PathBuf>| {
+                        repo.process_changes(&paths)
+                            .unwrap()
+                            .expect("every change is committed")
+                    },
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_staging);
+criterion_main!(benches);
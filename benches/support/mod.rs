@@ -0,0 +1,3 @@
+mod synthetic_repo;
+
+pub use synthetic_repo::*;
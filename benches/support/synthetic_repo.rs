@@ -0,0 +1,100 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use git2::{IndexAddOption, Repository};
+use gitwatch_rs::app_config::AppConfig;
+use tempfile::TempDir;
+
+const FILES_PER_DIR: usize = 100;
+const DIRS_PER_DIR: usize = 100;
+// every tenth file is ignored via .gitignore
+const IGNORED_EVERY: usize = 10;
+
+/// A repository with a committed working tree of generated files,
+/// nested like `dir0/sub42/file4217.txt` to resemble a real project
+pub struct SyntheticRepo {
+    // keeps the temp dir alive
+    _dir: TempDir,
+    // numbers the changes across all benchmarks, so every write differs from HEAD
+    changes: AtomicUsize,
+    /// All generated files, including the ignored ones
+    pub files: Vec<PathBuf>,
+    pub path: PathBuf,
+}
+
+impl SyntheticRepo {
+    pub fn new(file_count: usize) -> Result<Self> {
+        let dir = tempfile::tempdir()?;
+        // the repository path is canonicalized by gitwatch as well
+        let path = dir.path().canonicalize()?;
+        let repo = Repository::init(&path)?;
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Benchmark User")?;
+        config.set_str("user.email", "benchmark@example.com")?;
+
+        fs::write(path.join(".gitignore"), "*.log\n")?;
+        let files = (0..file_count)
+            .map(|i| Self::create_file(&path, i))
+            .collect::<Result<Vec<_>>>()?;
+        Self::create_initial_commit(&repo)?;
+
+        Ok(Self {
+            _dir: dir,
+            changes: AtomicUsize::new(0),
+            files,
+            path,
+        })
+    }
+
+    /// Settings of a `gitwatch watch` invocation without remote
+    pub fn app_config(&self) -> AppConfig {
+        AppConfig {
+            commit_message: Some("Benchmark commit".to_string()),
+            reconcile_interval_seconds: 600,
+            repository: self.path.clone(),
+            ..AppConfig::default()
+        }
+    }
+
+    /// Changes the content of a tracked file, a different one for each call
+    pub fn modify_file(&self) -> Result<PathBuf> {
+        let change = self.changes.fetch_add(1, Ordering::Relaxed) + 1;
+        // the tracked files are the ones in between the ignored ones
+        let index = (change * IGNORED_EVERY) % self.files.len();
+        let path = self.files[index].clone();
+        fs::write(&path, format!("change {change}\n"))?;
+        Ok(path)
+    }
+
+    fn create_file(root: &Path, i: usize) -> Result<PathBuf> {
+        let dir = root
+            .join(format!("dir{}", i / (FILES_PER_DIR * DIRS_PER_DIR)))
+            .join(format!("sub{}", (i / FILES_PER_DIR) % DIRS_PER_DIR));
+        if i.is_multiple_of(FILES_PER_DIR) {
+            fs::create_dir_all(&dir)?;
+        }
+        let extension = if i % IGNORED_EVERY == IGNORED_EVERY - 1 {
+            "log"
+        } else {
+            "txt"
+        };
+        let path = dir.join(format!("file{i}.{extension}"));
+        fs::write(&path, format!("content of file {i}\n"))?;
+        Ok(path)
+    }
+
+    fn create_initial_commit(repo: &Repository) -> Result<()> {
+        let mut index = repo.index()?;
+        index.add_all(["*"], IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+
+        let sig = repo.signature()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        repo.commit(Some("HEAD"), &sig, &sig, "Initial commit", &tree, &[])?;
+        Ok(())
+    }
+}
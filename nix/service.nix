@@ -14,6 +14,8 @@ let
     Install.WantedBy = [ "default.target" ];
 
     Service = {
+      Type = "notify";
+      WatchdogSec = 60;
       Environment = [
         "PATH=${lib.makeBinPath (cfg.extraPackages)}"
       ];
@@ -22,6 +24,7 @@ let
           args = cfg.args ++ [ cfg.repo_path ];
         in
         "${pkgs.gitwatch-rs}/bin/gitwatch watch ${lib.concatStringsSep " " args}";
+      ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
     };
   };
 in
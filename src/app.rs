@@ -1,48 +1,102 @@
-use crate::{app_config::AppConfig, filter::PathFilter};
-use std::{path::PathBuf, sync::mpsc::Receiver};
+use crate::{
+    app_config::AppConfig,
+    commit_message::CommitMessageProvider,
+    control::{ControlCommand, StopReason},
+    event_loop::WatchChannel,
+    events::EventEmitter,
+    filter::PathFilter,
+    lock::InstanceLock,
+    observer::GitwatchObserver,
+    sd_notify,
+    state::AppState,
+};
+use std::{
+    path::PathBuf,
+    sync::{mpsc::Receiver, Arc},
+};
 
 use anyhow::{Context, Result};
-use log::{debug, warn};
+use log::{debug, error, info, warn};
 
 use crate::{repo::GitwatchRepo, watcher::FileWatcher};
 
+/// Loads the current configuration, used to reload it while running
+pub type ConfigLoader = Box<dyn Fn() -> Result<AppConfig> + Send>;
+
+// Set via `AppBuilder`, kept across reloads
+#[derive(Clone, Default)]
+pub(crate) struct Extensions {
+    pub commit_message_provider: Option<Arc<dyn CommitMessageProvider>>,
+    pub observers: Vec<Arc<dyn GitwatchObserver>>,
+}
+
 pub struct App {
     commit_on_start: bool,
+    config_loader: Option<ConfigLoader>,
+    events: Arc<EventEmitter>,
+    extensions: Extensions,
+    // held as long as the app is running
+    _instance_lock: InstanceLock,
     path_filter: PathFilter,
     repo: GitwatchRepo,
     repo_path: PathBuf,
+    state: Arc<AppState>,
     watch: bool,
     watcher: FileWatcher,
 }
 
 impl App {
     pub fn new(config: AppConfig) -> Result<Self> {
+        Self::new_with_extensions(config, Extensions::default())
+    }
+
+    pub(crate) fn new_with_extensions(config: AppConfig, extensions: Extensions) -> Result<Self> {
         let repo_path = &config.repository;
-        let repo = GitwatchRepo::new(
-            repo_path,
-            config.commit_message,
-            config.commit_message_script,
-            config.ignore_regex.clone(),
-            config.dry_run,
-            config.remote,
-        )?;
-        let watcher = FileWatcher::new(config.debounce_seconds, config.retries);
+        let state = Arc::new(AppState::new(repo_path.clone()));
+        state.set_config(&config)?;
+        let events =
+            Arc::new(EventEmitter::new(&config).with_observers(extensions.observers.clone()));
+        state.set_events(&events);
+        let repo = create_repo(&config, &state, &events, &extensions)?;
+        let instance_lock = InstanceLock::acquire(repo.git_dir(), config.replace)?;
+        let watcher = FileWatcher::new(&config)
+            .with_state(state.clone())
+            .with_events(events.clone());
         let path_filter = PathFilter::new(repo_path, config.ignore_regex)?;
 
         Ok(Self {
             commit_on_start: config.commit_on_start,
+            config_loader: None,
+            events,
+            extensions,
+            _instance_lock: instance_lock,
             path_filter,
             repo,
             repo_path: config.repository,
+            state,
             watch: config.watch,
             watcher,
         })
     }
 
-    pub fn run(&self, shutdown_rx: Option<Receiver<()>>) -> Result<()> {
+    /// Enables reloading the configuration via `ControlCommand::Reload`
+    pub fn with_config_loader<L>(mut self, config_loader: L) -> Self
+    where
+        L: Fn() -> Result<AppConfig> + Send + 'static,
+    {
+        self.config_loader = Some(Box::new(config_loader));
+        self
+    }
+
+    /// State shared with the watch loop, e.g. to expose it via the control socket
+    pub fn state(&self) -> Arc<AppState> {
+        self.state.clone()
+    }
+
+    pub fn run(&mut self, control_rx: Option<Receiver<ControlCommand>>) -> Result<()> {
         if self.commit_on_start {
             self.repo
-                .process_changes()
+                .process_changes(&[])
                 .context("Failed to commit changes")?;
         }
 
@@ -51,18 +105,72 @@ impl App {
             return Ok(());
         }
 
-        self.watcher.watch(
-            &self.repo_path,
-            |paths| {
-                self.log_changed_paths(paths);
-                self.repo.process_changes()
-            },
-            |path| self.path_filter.is_path_ignored(path),
-            shutdown_rx,
-        )
+        // filesystem events & control commands wake up the watch loop through the same channel
+        let channel = WatchChannel::new();
+        if let Some(control_rx) = control_rx {
+            channel.forward_control(control_rx)?;
+        }
+
+        loop {
+            let stop_reason = self.watcher.watch(
+                &self.repo_path,
+                |paths| {
+                    self.log_changed_paths(paths);
+                    self.notify_changes_detected(paths);
+                    self.repo.process_changes(paths)
+                },
+                |path| {
+                    self.path_filter.is_path_ignored(path)
+                        && !self.repo.is_index_lock_released(path)
+                },
+                &channel,
+            )?;
+
+            match stop_reason {
+                StopReason::Shutdown => return Ok(()),
+                StopReason::Reload => {
+                    sd_notify::reloading();
+                    if let Err(e) = self.reload() {
+                        error!("Failed to reload configuration, keeping the current one: {e:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    fn reload(&mut self) -> Result<()> {
+        let Some(config_loader) = &self.config_loader else {
+            warn!("Reloading the configuration is not supported");
+            return Ok(());
+        };
+        let config = config_loader()?;
+        // the shared state (e.g. a manual pause) survives reloads
+        let events =
+            Arc::new(EventEmitter::new(&config).with_observers(self.extensions.observers.clone()));
+        let repo = create_repo(&config, &self.state, &events, &self.extensions)?;
+        let path_filter = PathFilter::new(&self.repo_path, config.ignore_regex.clone())?;
+        let watcher = FileWatcher::new(&config)
+            .with_state(self.state.clone())
+            .with_events(events.clone());
+
+        self.repo = repo;
+        self.path_filter = path_filter;
+        self.watcher = watcher;
+        // hooks of the old config may still be running, watching continues without waiting for them
+        self.state.set_events(&events);
+        std::mem::replace(&mut self.events, events).detach();
+        self.state.set_config(&config)?;
+        self.state.metrics().record_config_reload();
+        info!("Reloaded configuration");
+        Ok(())
     }
 
     fn log_changed_paths(&self, paths: &[PathBuf]) {
+        // interval commits are not triggered by specific paths
+        if paths.is_empty() {
+            return;
+        }
+
         let formatted_paths = paths
             .iter()
             .map(|p| p.strip_prefix(&self.repo_path).unwrap_or(p))
@@ -72,6 +180,30 @@ impl App {
 
         debug!("Detected changes:\n{formatted_paths}");
     }
+
+    fn notify_changes_detected(&self, paths: &[PathBuf]) {
+        if paths.is_empty() {
+            return;
+        }
+        for observer in &self.extensions.observers {
+            observer.on_changes_detected(paths);
+        }
+    }
+}
+
+fn create_repo(
+    config: &AppConfig,
+    state: &Arc<AppState>,
+    events: &Arc<EventEmitter>,
+    extensions: &Extensions,
+) -> Result<GitwatchRepo> {
+    let mut repo = GitwatchRepo::new(config)?
+        .with_state(state.clone())
+        .with_events(events.clone());
+    if let Some(provider) = &extensions.commit_message_provider {
+        repo = repo.with_commit_message_provider(provider.clone());
+    }
+    Ok(repo)
 }
 
 #[cfg(test)]
@@ -98,7 +230,7 @@ mod tests {
             watch: false,
             ..AppConfig::default()
         };
-        let app = App::new(config)?;
+        let mut app = App::new(config)?;
         app.run(None)?;
 
         assert!(!git_repo.statuses(None)?.is_empty());
@@ -121,7 +253,7 @@ mod tests {
             watch: false,
             ..AppConfig::default()
         };
-        let app = App::new(config)?;
+        let mut app = App::new(config)?;
         let result = app.run(None);
         assert!(result.is_err());
         assert!(result
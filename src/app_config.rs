@@ -1,27 +1,71 @@
-use crate::{cli::CliOptions, config_file::ConfigFile, util::normalize_path};
+use crate::{
+    branch::BranchPolicy,
+    cli::CliOptions,
+    config_file::ConfigFile,
+    events::EventHooks,
+    filter::PathFilter,
+    log_file::LogFile,
+    stage::StagePolicy,
+    util::normalize_path,
+    watcher::{CommitMode, WatcherBackend},
+    webhook::Webhook,
+};
 use anyhow::{bail, Context, Result};
+use log::warn;
 use regex::Regex;
-use std::path::PathBuf;
+use serde::Serialize;
+use std::{net::SocketAddr, path::PathBuf};
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct AppConfig {
+    pub branch_policy: BranchPolicy,
+    pub commit_interval_seconds: u64,
     pub commit_message: Option<String>,
     pub commit_message_script: Option<PathBuf>,
+    pub commit_mode: CommitMode,
     pub commit_on_start: bool,
     pub debounce_seconds: u64,
     pub dry_run: bool,
+    pub event_hooks: EventHooks,
+    #[serde(with = "serde_regex")]
     pub ignore_regex: Option<Regex>,
+    pub log_file: Option<LogFile>,
+    pub max_commits_per_hour: Option<u32>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub min_commit_interval_seconds: u64,
+    pub poll_interval_seconds: u64,
+    pub reconcile_interval_seconds: u64,
     pub remote: Option<String>,
+    // shut down another instance watching the repository instead of failing
+    pub replace: bool,
     pub repository: PathBuf,
     pub retries: i32,
+    pub run_hooks: bool,
+    pub stage_policy: StagePolicy,
     pub watch: bool,
+    pub watcher_backend: WatcherBackend,
+    pub webhooks: Vec<Webhook>,
 }
 
 impl AppConfig {
     pub fn new(cli_config: CliOptions) -> Result<Self> {
         // load config file if it exists
-        let file_config = ConfigFile::load(&cli_config.repository).unwrap_or_default();
+        let file_config = ConfigFile::load(&cli_config.repository).unwrap_or_else(|e| {
+            warn!("Ignoring invalid config file: {e}");
+            ConfigFile::default()
+        });
+        Self::from_config_file(cli_config, file_config)
+    }
+
+    /// Like `new`, but fails on an invalid config file instead of falling back to the cli flags,
+    /// so a reload keeps the running configuration
+    pub fn reload(cli_config: CliOptions) -> Result<Self> {
+        let file_config =
+            ConfigFile::load(&cli_config.repository).context("Invalid config file")?;
+        Self::from_config_file(cli_config, file_config)
+    }
 
+    fn from_config_file(cli_config: CliOptions, file_config: ConfigFile) -> Result<Self> {
         let repository = normalize_path(&cli_config.repository).context(format!(
             "Invalid repository path '{}'",
             cli_config.repository.display()
@@ -39,6 +83,25 @@ impl AppConfig {
         cli_config: CliOptions,
         file_config: ConfigFile,
     ) -> Result<Self> {
+        let branch_policy = BranchPolicy {
+            allowed_branches: file_config
+                .allowed_branches
+                .unwrap_or(cli_config.branch.allowed_branches),
+            denied_branches: file_config
+                .denied_branches
+                .unwrap_or(cli_config.branch.denied_branches),
+            detached_head: file_config
+                .detached_head
+                .unwrap_or(cli_config.branch.detached_head),
+            on_branch_switch: file_config
+                .on_branch_switch
+                .unwrap_or(cli_config.branch.on_branch_switch),
+        };
+
+        let commit_interval_seconds = file_config
+            .commit_interval_seconds
+            .unwrap_or(cli_config.commit_interval_seconds);
+
         let commit_message = file_config
             .commit_message
             .or(cli_config.commit_message.message);
@@ -60,6 +123,8 @@ impl AppConfig {
             })
             .transpose()?;
 
+        let commit_mode = file_config.commit_mode.unwrap_or(cli_config.commit_mode);
+
         let commit_on_start = file_config
             .commit_on_start
             .unwrap_or(cli_config.commit_on_start);
@@ -70,12 +135,67 @@ impl AppConfig {
 
         let dry_run = file_config.dry_run.unwrap_or(cli_config.dry_run);
 
+        let event_hooks = EventHooks {
+            on_commit: file_config.on_commit.or(cli_config.event_hooks.on_commit),
+            on_error: file_config.on_error.or(cli_config.event_hooks.on_error),
+            on_pause: file_config.on_pause.or(cli_config.event_hooks.on_pause),
+            on_push: file_config.on_push.or(cli_config.event_hooks.on_push),
+            on_push_failure: file_config
+                .on_push_failure
+                .or(cli_config.event_hooks.on_push_failure),
+        };
+
         let ignore_regex = if let Some(regex) = file_config.ignore_regex {
             Some(regex)
         } else {
             cli_config.ignore_regex
         };
 
+        let log_file_config = file_config.log_file.unwrap_or_default();
+        let log_file = log_file_config
+            .path
+            .or(cli_config.log_file.path)
+            .map(|path| -> Result<LogFile> {
+                let path_str = path.to_str().context("Invalid log file path")?;
+                let path = PathBuf::from(shellexpand::full(path_str)?.as_ref());
+                Ok(LogFile {
+                    level: log_file_config
+                        .level
+                        .or(cli_config.log_file.level)
+                        .unwrap_or(cli_config.log_level),
+                    max_size_mb: log_file_config
+                        .max_size_mb
+                        .unwrap_or(cli_config.log_file.max_size_mb),
+                    // if relative path, interpret it relative to repository root
+                    path: repository.join(path),
+                    retention: log_file_config
+                        .retention
+                        .unwrap_or(cli_config.log_file.retention),
+                    rotation: log_file_config
+                        .rotation
+                        .unwrap_or(cli_config.log_file.rotation),
+                })
+            })
+            .transpose()?;
+
+        let max_commits_per_hour = file_config
+            .max_commits_per_hour
+            .or(cli_config.max_commits_per_hour);
+
+        let metrics_addr = file_config.metrics_addr.or(cli_config.metrics_addr);
+
+        let min_commit_interval_seconds = file_config
+            .min_commit_interval_seconds
+            .unwrap_or(cli_config.min_commit_interval_seconds);
+
+        let poll_interval_seconds = file_config
+            .poll_interval_seconds
+            .unwrap_or(cli_config.poll_interval_seconds);
+
+        let reconcile_interval_seconds = file_config
+            .reconcile_interval_seconds
+            .unwrap_or(cli_config.reconcile_interval_seconds);
+
         let remote = if let Some(remote) = file_config.remote {
             Some(remote)
         } else {
@@ -84,27 +204,104 @@ impl AppConfig {
 
         let retries = file_config.retries.unwrap_or(cli_config.retries);
 
+        let run_hooks = file_config.run_hooks.unwrap_or(cli_config.run_hooks);
+
+        let stage_config = file_config.stage.unwrap_or_default();
+        let stage_policy = StagePolicy {
+            deleted: stage_config.deleted.unwrap_or(cli_config.stage.deleted),
+            max_deletions: stage_config
+                .max_deletions
+                .or(cli_config.stage.max_deletions),
+            max_deletion_percent: stage_config
+                .max_deletion_percent
+                .or(cli_config.stage.max_deletion_percent),
+            modified: stage_config.modified.unwrap_or(cli_config.stage.modified),
+            renamed: stage_config.renamed.unwrap_or(cli_config.stage.renamed),
+            untracked: stage_config.untracked.unwrap_or(cli_config.stage.untracked),
+        };
+
         let watch = file_config.watch.unwrap_or(cli_config.watch);
 
+        let watcher_backend = file_config
+            .watcher_backend
+            .unwrap_or(cli_config.watcher_backend);
+
+        let webhooks = file_config.webhooks.unwrap_or_else(|| {
+            cli_config
+                .webhooks
+                .into_iter()
+                .map(Webhook::new)
+                .collect()
+        });
+
         Ok(Self {
             repository,
+            branch_policy,
+            commit_interval_seconds,
             commit_message,
             commit_message_script,
+            commit_mode,
             commit_on_start,
             debounce_seconds,
             dry_run,
+            event_hooks,
             ignore_regex,
+            log_file,
+            max_commits_per_hour,
+            metrics_addr,
+            min_commit_interval_seconds,
+            poll_interval_seconds,
+            reconcile_interval_seconds,
             remote,
+            // only a CLI flag, it has no meaning in the config file
+            replace: cli_config.replace,
             retries,
+            run_hooks,
+            stage_policy,
             watch,
+            watcher_backend,
+            webhooks,
         })
     }
 
-    fn validate(&self) -> Result<()> {
+    pub(crate) fn validate(&self) -> Result<()> {
+        self.validate_settings()?;
+        self.validate_commit_message()
+    }
+
+    // everything but the commit message, which a custom `CommitMessageProvider` replaces
+    pub(crate) fn validate_settings(&self) -> Result<()> {
         if self.retries < -1 {
             bail!("Retry count must be >= -1");
         }
 
+        if self.commit_mode != CommitMode::Debounce && self.commit_interval_seconds == 0 {
+            bail!("Commit interval must be at least 1 second");
+        }
+
+        if self.max_commits_per_hour == Some(0) {
+            bail!("Max commits per hour must be at least 1");
+        }
+
+        if self.poll_interval_seconds == 0 {
+            bail!("Poll interval must be at least 1 second");
+        }
+
+        if let Some(max_percent) = self.stage_policy.max_deletion_percent {
+            if !(0.0..=100.0).contains(&max_percent) {
+                bail!("Max deletion percent must be between 0 and 100");
+            }
+        }
+
+        if let Some(branch) = self
+            .branch_policy
+            .allowed_branches
+            .iter()
+            .find(|branch| self.branch_policy.denied_branches.contains(branch))
+        {
+            bail!("Branch '{branch}' cannot be both allowed and denied");
+        }
+
         if !self.repository.exists() {
             bail!(
                 "Repository path does not exist: {}",
@@ -112,6 +309,34 @@ impl AppConfig {
             );
         }
 
+        if let Some(log_file) = &self.log_file {
+            if log_file.max_size_mb == 0 {
+                bail!("Log file max size must be at least 1 MB");
+            }
+            // otherwise every log record would trigger another commit
+            let path_filter = PathFilter::new(&self.repository, self.ignore_regex.clone())?;
+            if log_file.path.starts_with(&self.repository) {
+                // the rotated files are created next to the log file
+                if let Some(path) = log_file
+                    .sample_paths()
+                    .into_iter()
+                    .find(|path| !path_filter.is_path_ignored(path))
+                {
+                    bail!(
+                        "Log file {} is inside the repository, ignore it & its rotated files via .gitignore or place it in .git/",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        for webhook in &self.webhooks {
+            webhook.validate()?;
+        }
+        Ok(())
+    }
+
+    fn validate_commit_message(&self) -> Result<()> {
         match (&self.commit_message, &self.commit_message_script) {
             (None, None) => {
                 bail!("Either commit-message or commit-message-script must be set")
@@ -148,7 +373,11 @@ mod tests {
 
     use super::*;
     use crate::{
-        cli::{CommitMessageOptions, LogLevel},
+        branch::DetachedHeadPolicy,
+        cli::{
+            BranchOptions, CommitMessageOptions, EventHookOptions, LogFileOptions, LogFormat, LogLevel, StageOptions,
+        },
+        log_file::LogRotation,
         test_support::constants::TEST_COMMIT_MESSAGE,
     };
 
@@ -165,13 +394,28 @@ mod tests {
                     .unwrap_or(other.repository.clone())
                 && self.ignore_regex.as_ref().map(|r| r.as_str())
                     == other.ignore_regex.as_ref().map(|r| r.as_str())
+                && self.branch_policy == other.branch_policy
+                && self.commit_interval_seconds == other.commit_interval_seconds
                 && self.commit_message == other.commit_message
                 && self.commit_message_script == other.commit_message_script
+                && self.commit_mode == other.commit_mode
                 && self.debounce_seconds == other.debounce_seconds
                 && self.dry_run == other.dry_run
+                && self.event_hooks == other.event_hooks
+                && self.log_file == other.log_file
+                && self.max_commits_per_hour == other.max_commits_per_hour
+                && self.metrics_addr == other.metrics_addr
+                && self.min_commit_interval_seconds == other.min_commit_interval_seconds
+                && self.poll_interval_seconds == other.poll_interval_seconds
+                && self.reconcile_interval_seconds == other.reconcile_interval_seconds
+                && self.replace == other.replace
                 && self.retries == other.retries
+                && self.run_hooks == other.run_hooks
+                && self.stage_policy == other.stage_policy
                 && self.commit_on_start == other.commit_on_start
                 && self.watch == other.watch
+                && self.watcher_backend == other.watcher_backend
+                && self.webhooks == other.webhooks
         }
     }
 
@@ -191,21 +435,56 @@ mod tests {
             "--watch=true",
             "--dry-run",
             "--remote=origin",
+            "--run-hooks",
+            "--denied-branches=main,master",
+            "--detached-head=error",
+            "--stage-untracked=false",
+            "--max-deletions=10",
+            "--watcher-backend=poll",
+            "--poll-interval-seconds=10",
+            "--commit-mode=combined",
+            "--commit-interval-seconds=600",
+            "--min-commit-interval-seconds=60",
+            "--max-commits-per-hour=20",
+            "--metrics-addr=127.0.0.1:9184",
         ]);
 
         let config = AppConfig::new(watch_opts)?;
 
         let expected = AppConfig {
             repository: temp_dir.path().to_path_buf(),
+            branch_policy: BranchPolicy {
+                denied_branches: vec!["main".to_string(), "master".to_string()],
+                detached_head: DetachedHeadPolicy::Error,
+                ..BranchPolicy::default()
+            },
+            commit_interval_seconds: 600,
             commit_message: Some(TEST_COMMIT_MESSAGE.to_string()),
             commit_message_script: None,
+            commit_mode: CommitMode::Combined,
             debounce_seconds: 0,
             ignore_regex: Some(Regex::new("/ignore-me/.*")?),
+            log_file: None,
             dry_run: true,
+            event_hooks: EventHooks::default(),
+            max_commits_per_hour: Some(20),
+            metrics_addr: Some("127.0.0.1:9184".parse()?),
+            min_commit_interval_seconds: 60,
+            poll_interval_seconds: 10,
+            reconcile_interval_seconds: 600,
             retries: 2,
+            run_hooks: true,
+            stage_policy: StagePolicy {
+                untracked: false,
+                max_deletions: Some(10),
+                ..StagePolicy::default()
+            },
             commit_on_start: false,
             watch: true,
+            watcher_backend: WatcherBackend::Poll,
+            webhooks: Vec::new(),
             remote: Some("origin".to_string()),
+            replace: false,
         };
 
         assert_eq!(config, expected);
@@ -233,6 +512,29 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_reload_invalid_config_file() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        fs::write(temp_dir.path().join("gitwatch.yaml"), "commit_message: [")?;
+        let watch_opts = CliOptions::parse_from([
+            "gitwatch",
+            temp_dir.path().to_str().unwrap(),
+            "--commit-message",
+            TEST_COMMIT_MESSAGE,
+        ]);
+
+        // on startup the invalid config file is ignored
+        let config = AppConfig::new(watch_opts.clone())?;
+        assert_eq!(config.commit_message.as_deref(), Some(TEST_COMMIT_MESSAGE));
+
+        let err = AppConfig::reload(watch_opts).unwrap_err();
+        assert!(
+            format!("{err:#}").contains("Invalid config file"),
+            "Unexpected error message: {err:#}"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_config_validation() -> TestResult {
         let temp_dir = tempfile::tempdir()?;
@@ -243,15 +545,30 @@ mod tests {
 
         let valid_config = AppConfig {
             repository: repo_path.clone(),
+            branch_policy: BranchPolicy::default(),
+            commit_interval_seconds: 300,
             commit_message: Some("test".to_string()),
             commit_message_script: None,
+            commit_mode: CommitMode::Debounce,
             commit_on_start: true,
             debounce_seconds: 0,
             ignore_regex: None,
+            log_file: None,
+            max_commits_per_hour: None,
+            metrics_addr: None,
+            min_commit_interval_seconds: 0,
+            poll_interval_seconds: 5,
+            reconcile_interval_seconds: 600,
             watch: true,
+            watcher_backend: WatcherBackend::Native,
+            webhooks: Vec::new(),
             retries: 3,
+            run_hooks: false,
+            stage_policy: StagePolicy::default(),
             dry_run: false,
+            event_hooks: EventHooks::default(),
             remote: None,
+            replace: false,
         };
         assert!(valid_config.validate().is_ok());
 
@@ -320,6 +637,59 @@ mod tests {
             .to_string()
             .contains("Commit message script path is not a file"));
 
+        let invalid_commit_interval = AppConfig {
+            commit_interval_seconds: 0,
+            commit_mode: CommitMode::Interval,
+            ..valid_config.clone()
+        };
+        assert_eq!(
+            invalid_commit_interval.validate().unwrap_err().to_string(),
+            "Commit interval must be at least 1 second"
+        );
+
+        let invalid_max_commits_per_hour = AppConfig {
+            max_commits_per_hour: Some(0),
+            ..valid_config.clone()
+        };
+        assert_eq!(
+            invalid_max_commits_per_hour
+                .validate()
+                .unwrap_err()
+                .to_string(),
+            "Max commits per hour must be at least 1"
+        );
+
+        let invalid_max_deletion_percent = AppConfig {
+            stage_policy: StagePolicy {
+                max_deletion_percent: Some(150.0),
+                ..StagePolicy::default()
+            },
+            ..valid_config.clone()
+        };
+        assert_eq!(
+            invalid_max_deletion_percent
+                .validate()
+                .unwrap_err()
+                .to_string(),
+            "Max deletion percent must be between 0 and 100"
+        );
+
+        let conflicting_branch_policy = AppConfig {
+            branch_policy: BranchPolicy {
+                allowed_branches: vec!["main".to_string()],
+                denied_branches: vec!["main".to_string()],
+                ..BranchPolicy::default()
+            },
+            ..valid_config.clone()
+        };
+        assert_eq!(
+            conflicting_branch_policy
+                .validate()
+                .unwrap_err()
+                .to_string(),
+            "Branch 'main' cannot be both allowed and denied"
+        );
+
         let nonexistent_repo_path = AppConfig {
             repository: PathBuf::from("/nonexistent/path"),
             ..valid_config.clone()
@@ -342,18 +712,34 @@ mod tests {
 
         let cli_opts = CliOptions {
             repository: PathBuf::from_str(".")?,
+            branch: BranchOptions::default(),
+            commit_interval_seconds: 300,
             commit_message: CommitMessageOptions {
                 message: None,
                 script: Some(PathBuf::from_str("./commit-msg.sh")?),
             },
+            commit_mode: CommitMode::Debounce,
             commit_on_start: true,
             debounce_seconds: 0,
             ignore_regex: None,
             watch: true,
             retries: 3,
+            run_hooks: false,
+            stage: StageOptions::default(),
             dry_run: false,
             remote: None,
+            replace: false,
+            event_hooks: EventHookOptions::default(),
+            log_file: LogFileOptions::default(),
+            log_format: LogFormat::Text,
             log_level: LogLevel::Info,
+            max_commits_per_hour: None,
+            metrics_addr: None,
+            min_commit_interval_seconds: 0,
+            poll_interval_seconds: 5,
+            reconcile_interval_seconds: 600,
+            watcher_backend: WatcherBackend::Native,
+            webhooks: Vec::new(),
         };
 
         let config = AppConfig::new(cli_opts)?;
@@ -373,18 +759,34 @@ mod tests {
 
         let cli_opts = CliOptions {
             repository: repo_path.to_path_buf(),
+            branch: BranchOptions::default(),
+            commit_interval_seconds: 300,
             commit_message: CommitMessageOptions {
                 message: None,
                 script: Some(commit_message_script_path.clone()),
             },
+            commit_mode: CommitMode::Debounce,
             commit_on_start: true,
             debounce_seconds: 0,
             ignore_regex: None,
             watch: true,
             retries: 3,
+            run_hooks: false,
+            stage: StageOptions::default(),
             dry_run: false,
             remote: None,
+            replace: false,
+            event_hooks: EventHookOptions::default(),
+            log_file: LogFileOptions::default(),
+            log_format: LogFormat::Text,
             log_level: LogLevel::Info,
+            max_commits_per_hour: None,
+            metrics_addr: None,
+            min_commit_interval_seconds: 0,
+            poll_interval_seconds: 5,
+            reconcile_interval_seconds: 600,
+            watcher_backend: WatcherBackend::Native,
+            webhooks: Vec::new(),
         };
 
         let config = AppConfig::new(cli_opts)?;
@@ -409,7 +811,10 @@ mod tests {
         assert_eq!(config.ignore_regex.unwrap().as_str(), "cli_ignore.*");
         assert_eq!(config.remote.unwrap(), "cli_remote");
         assert_eq!(config.retries, 3);
+        assert!(!config.run_hooks);
         assert!(config.watch);
+        assert_eq!(config.watcher_backend, WatcherBackend::Native);
+        assert_eq!(config.commit_mode, CommitMode::Debounce);
 
         Ok(())
     }
@@ -431,7 +836,10 @@ mod tests {
         assert_eq!(config.ignore_regex.unwrap().as_str(), "file_ignore.*");
         assert_eq!(config.remote.unwrap(), "file_remote");
         assert_eq!(config.retries, 5);
+        assert!(config.run_hooks);
         assert!(!config.watch);
+        assert_eq!(config.watcher_backend, WatcherBackend::Poll);
+        assert_eq!(config.commit_mode, CommitMode::Interval);
 
         Ok(())
     }
@@ -463,21 +871,80 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_log_file() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let repo_path = temp_dir.path();
+        let mut cli_opts = create_test_cli_options(repo_path)?;
+        cli_opts.log_file.path = Some(PathBuf::from(".git/gitwatch.log"));
+        cli_opts.log_file.rotation = LogRotation::Size;
+        fs::write(
+            repo_path.join("gitwatch.yaml"),
+            "log_file:\n  level: debug\n  retention: 3\n",
+        )?;
+
+        let config = AppConfig::new(cli_opts.clone())?;
+        assert_eq!(
+            config.log_file,
+            Some(LogFile {
+                level: LogLevel::Debug,
+                max_size_mb: 10,
+                path: repo_path.canonicalize()?.join(".git/gitwatch.log"),
+                retention: 3,
+                rotation: LogRotation::Size,
+            })
+        );
+
+        // the log file would trigger commits
+        cli_opts.log_file.path = Some(PathBuf::from("gitwatch.log"));
+        let err = AppConfig::new(cli_opts.clone()).unwrap_err();
+        assert!(
+            err.to_string().contains("is inside the repository"),
+            "Unexpected error: {err}"
+        );
+        // as well as the rotated files
+        fs::write(repo_path.join(".gitignore"), "*.log\n")?;
+        let err = AppConfig::new(cli_opts.clone()).unwrap_err();
+        assert!(
+            err.to_string().contains("gitwatch.log.1 is inside the repository"),
+            "Unexpected error: {err}"
+        );
+        fs::write(repo_path.join(".gitignore"), "*.log\n*.log.*\n")?;
+        AppConfig::new(cli_opts)?;
+        Ok(())
+    }
+
     fn create_test_cli_options(repo_path: &Path) -> Result<CliOptions> {
         Ok(CliOptions {
             repository: repo_path.to_path_buf(),
+            branch: BranchOptions::default(),
+            commit_interval_seconds: 300,
             commit_message: CommitMessageOptions {
                 message: Some("cli message".to_string()),
                 script: None,
             },
+            commit_mode: CommitMode::Debounce,
             commit_on_start: true,
             debounce_seconds: 1,
             dry_run: false,
             ignore_regex: Some(Regex::new("cli_ignore.*").unwrap()),
+            event_hooks: EventHookOptions::default(),
+            log_file: LogFileOptions::default(),
+            log_format: LogFormat::Text,
             log_level: LogLevel::Info,
+            max_commits_per_hour: None,
+            metrics_addr: None,
+            min_commit_interval_seconds: 0,
+            poll_interval_seconds: 5,
+            reconcile_interval_seconds: 600,
             remote: Some("cli_remote".to_string()),
+            replace: false,
             retries: 3,
+            run_hooks: false,
+            stage: StageOptions::default(),
             watch: true,
+            watcher_backend: WatcherBackend::Native,
+            webhooks: Vec::new(),
         })
     }
 
@@ -491,7 +958,10 @@ mod tests {
         log_level: "debug"
         remote: "file_remote"
         retries: 5
+        run_hooks: true
         watch: false
+        watcher_backend: "poll"
+        commit_mode: "interval"
         "#;
 
         fs::write(dir.join("gitwatch.yaml"), config_content)?;
@@ -0,0 +1,126 @@
+use std::{
+    path::PathBuf,
+    sync::{mpsc, Arc},
+};
+
+use anyhow::{Context, Result};
+use tokio::{sync::mpsc::UnboundedSender, task};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::sync::CancellationToken;
+
+use crate::{app::App, builder::AppBuilder, control::ControlCommand, observer::GitwatchObserver};
+
+/// An event of a running `App`, see `GitwatchObserver` for details
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppEvent {
+    ChangesDetected(Vec<PathBuf>),
+    Commit {
+        commit_id: String,
+        files: Vec<String>,
+        message: String,
+    },
+    Push {
+        remote: String,
+    },
+    PushFailure {
+        remote: String,
+        error: String,
+    },
+    Error {
+        error: String,
+    },
+    Paused {
+        reason: String,
+    },
+}
+
+/// Stream of the events of a running `App`, ends once the app is dropped
+pub type EventStream = UnboundedReceiverStream<AppEvent>;
+
+/// Creates an observer that forwards all events to the returned stream,
+/// add it via `AppBuilder::with_observer`
+pub fn event_channel() -> (Arc<dyn GitwatchObserver>, EventStream) {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    (
+        Arc::new(ChannelObserver { sender }),
+        UnboundedReceiverStream::new(receiver),
+    )
+}
+
+struct ChannelObserver {
+    sender: UnboundedSender<AppEvent>,
+}
+
+impl ChannelObserver {
+    fn send(&self, event: AppEvent) {
+        // the stream may have been dropped, which is not an error for the app
+        let _ = self.sender.send(event);
+    }
+}
+
+impl GitwatchObserver for ChannelObserver {
+    fn on_changes_detected(&self, paths: &[PathBuf]) {
+        self.send(AppEvent::ChangesDetected(paths.to_vec()));
+    }
+
+    fn on_commit(&self, commit_id: &str, files: &[String], message: &str) {
+        self.send(AppEvent::Commit {
+            commit_id: commit_id.to_string(),
+            files: files.to_vec(),
+            message: message.to_string(),
+        });
+    }
+
+    fn on_push(&self, remote: &str) {
+        self.send(AppEvent::Push {
+            remote: remote.to_string(),
+        });
+    }
+
+    fn on_push_failure(&self, remote: &str, error: &str) {
+        self.send(AppEvent::PushFailure {
+            remote: remote.to_string(),
+            error: error.to_string(),
+        });
+    }
+
+    fn on_error(&self, error: &str) {
+        self.send(AppEvent::Error {
+            error: error.to_string(),
+        });
+    }
+
+    fn on_paused(&self, reason: &str) {
+        self.send(AppEvent::Paused {
+            reason: reason.to_string(),
+        });
+    }
+}
+
+impl App {
+    /// Runs the app on tokio's blocking thread pool until it fails or `cancellation` is cancelled.
+    /// Dropping the future shuts the app down as well, but without waiting for it.
+    pub async fn run_async(mut self, cancellation: CancellationToken) -> Result<()> {
+        let (control_tx, control_rx) = mpsc::channel();
+        let cancellation = cancellation.child_token();
+        let _drop_guard = cancellation.clone().drop_guard();
+
+        let shutdown = task::spawn(async move {
+            cancellation.cancelled().await;
+            // the app may already have stopped
+            let _ = control_tx.send(ControlCommand::Shutdown);
+        });
+        let result = task::spawn_blocking(move || self.run(Some(control_rx))).await;
+        shutdown.abort();
+        result.context("The watch task panicked")?
+    }
+}
+
+impl AppBuilder {
+    /// Like `build`, but opens the repository on tokio's blocking thread pool
+    pub async fn build_async(self) -> Result<App> {
+        task::spawn_blocking(move || self.build())
+            .await
+            .context("The build task panicked")?
+    }
+}
@@ -0,0 +1,83 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BranchPolicy {
+    pub allowed_branches: Vec<String>,
+    pub denied_branches: Vec<String>,
+    pub detached_head: DetachedHeadPolicy,
+    pub on_branch_switch: BranchSwitchPolicy,
+}
+
+/// What to do when HEAD does not point to a branch
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DetachedHeadPolicy {
+    // pause autocommits until a branch is checked out
+    #[default]
+    Skip,
+    // commit, but don't push
+    Commit,
+    // fail with an error
+    Error,
+}
+
+/// What to do when the checked out branch changes while watching
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BranchSwitchPolicy {
+    // log the switch and keep committing to the new branch
+    #[default]
+    Log,
+    // pause autocommits until the initial branch is checked out again
+    Pause,
+}
+
+impl BranchPolicy {
+    /// Returns the reason why autocommits on `branch` are not permitted, if any
+    pub fn check_branch(&self, branch: &str) -> Option<String> {
+        if self.denied_branches.iter().any(|denied| denied == branch) {
+            return Some(format!("branch '{branch}' is denied"));
+        }
+        if !self.allowed_branches.is_empty()
+            && !self
+                .allowed_branches
+                .iter()
+                .any(|allowed| allowed == branch)
+        {
+            return Some(format!("branch '{branch}' is not allowed"));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_branch() {
+        let policy = BranchPolicy::default();
+        assert_eq!(policy.check_branch("main"), None);
+
+        let policy = BranchPolicy {
+            denied_branches: vec!["main".to_string()],
+            ..BranchPolicy::default()
+        };
+        assert_eq!(
+            policy.check_branch("main"),
+            Some("branch 'main' is denied".to_string())
+        );
+        assert_eq!(policy.check_branch("notes"), None);
+
+        let policy = BranchPolicy {
+            allowed_branches: vec!["notes".to_string()],
+            ..BranchPolicy::default()
+        };
+        assert_eq!(
+            policy.check_branch("main"),
+            Some("branch 'main' is not allowed".to_string())
+        );
+        assert_eq!(policy.check_branch("notes"), None);
+    }
+}
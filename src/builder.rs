@@ -0,0 +1,273 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::{
+    app::{App, Extensions},
+    app_config::AppConfig,
+    branch::BranchPolicy,
+    commit_message::CommitMessageProvider,
+    events::EventHooks,
+    observer::GitwatchObserver,
+    stage::StagePolicy,
+    util::normalize_path,
+    watcher::{CommitMode, WatcherBackend},
+    webhook::Webhook,
+};
+
+/// Builds an `App` for embedding gitwatch in other tools, with the same defaults as the CLI.
+/// Unlike the CLI, the builder does not read a `gitwatch.yaml` config file.
+///
+/// ```no_run
+/// # fn main() -> anyhow::Result<()> {
+/// let mut app = gitwatch_rs::builder::AppBuilder::new("/path/to/repo")
+///     .with_commit_message("Auto commit")
+///     .with_remote("origin")
+///     .build()?;
+/// app.run(None)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AppBuilder {
+    config: AppConfig,
+    extensions: Extensions,
+}
+
+impl AppBuilder {
+    pub fn new(repository: impl Into<PathBuf>) -> Self {
+        Self {
+            config: AppConfig {
+                commit_interval_seconds: 300,
+                commit_on_start: true,
+                debounce_seconds: 1,
+                poll_interval_seconds: 5,
+                reconcile_interval_seconds: 600,
+                repository: repository.into(),
+                retries: 3,
+                watch: true,
+                ..AppConfig::default()
+            },
+            extensions: Extensions::default(),
+        }
+    }
+
+    pub fn with_branch_policy(mut self, branch_policy: BranchPolicy) -> Self {
+        self.config.branch_policy = branch_policy;
+        self
+    }
+
+    pub fn with_commit_interval_seconds(mut self, seconds: u64) -> Self {
+        self.config.commit_interval_seconds = seconds;
+        self
+    }
+
+    /// Replaces a previously set commit message script
+    pub fn with_commit_message(mut self, message: impl Into<String>) -> Self {
+        self.config.commit_message = Some(message.into());
+        self.config.commit_message_script = None;
+        self
+    }
+
+    /// Replaces a previously set commit message.
+    /// A relative path is relative to the repository.
+    pub fn with_commit_message_script(mut self, script_path: impl Into<PathBuf>) -> Self {
+        self.config.commit_message = None;
+        self.config.commit_message_script = Some(script_path.into());
+        self
+    }
+
+    /// Generates commit messages with `provider`, e.g. a `TemplateMessage` or a custom implementation.
+    /// Takes precedence over a commit message or script.
+    pub fn with_commit_message_provider(
+        mut self,
+        provider: Arc<dyn CommitMessageProvider>,
+    ) -> Self {
+        self.extensions.commit_message_provider = Some(provider);
+        self
+    }
+
+    pub fn with_commit_mode(mut self, commit_mode: CommitMode) -> Self {
+        self.config.commit_mode = commit_mode;
+        self
+    }
+
+    pub fn with_commit_on_start(mut self, commit_on_start: bool) -> Self {
+        self.config.commit_on_start = commit_on_start;
+        self
+    }
+
+    pub fn with_debounce_seconds(mut self, seconds: u64) -> Self {
+        self.config.debounce_seconds = seconds;
+        self
+    }
+
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.config.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_event_hooks(mut self, event_hooks: EventHooks) -> Self {
+        self.config.event_hooks = event_hooks;
+        self
+    }
+
+    pub fn with_ignore_regex(mut self, ignore_regex: Regex) -> Self {
+        self.config.ignore_regex = Some(ignore_regex);
+        self
+    }
+
+    pub fn with_max_commits_per_hour(mut self, max_commits: u32) -> Self {
+        self.config.max_commits_per_hour = Some(max_commits);
+        self
+    }
+
+    pub fn with_min_commit_interval_seconds(mut self, seconds: u64) -> Self {
+        self.config.min_commit_interval_seconds = seconds;
+        self
+    }
+
+    /// Adds an observer that is notified of changes, commits, pushes, errors & pauses
+    pub fn with_observer(mut self, observer: Arc<dyn GitwatchObserver>) -> Self {
+        self.extensions.observers.push(observer);
+        self
+    }
+
+    pub fn with_poll_interval_seconds(mut self, seconds: u64) -> Self {
+        self.config.poll_interval_seconds = seconds;
+        self
+    }
+
+    /// Scans the whole working tree every `seconds`, 0 scans it for every commit
+    pub fn with_reconcile_interval_seconds(mut self, seconds: u64) -> Self {
+        self.config.reconcile_interval_seconds = seconds;
+        self
+    }
+
+    pub fn with_remote(mut self, remote: impl Into<String>) -> Self {
+        self.config.remote = Some(remote.into());
+        self
+    }
+
+    /// Shuts down another instance watching the repository instead of failing
+    pub fn with_replace(mut self, replace: bool) -> Self {
+        self.config.replace = replace;
+        self
+    }
+
+    /// Number of retries if committing fails, -1 retries infinitely
+    pub fn with_retries(mut self, retries: i32) -> Self {
+        self.config.retries = retries;
+        self
+    }
+
+    pub fn with_run_hooks(mut self, run_hooks: bool) -> Self {
+        self.config.run_hooks = run_hooks;
+        self
+    }
+
+    pub fn with_stage_policy(mut self, stage_policy: StagePolicy) -> Self {
+        self.config.stage_policy = stage_policy;
+        self
+    }
+
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.config.watch = watch;
+        self
+    }
+
+    pub fn with_watcher_backend(mut self, watcher_backend: WatcherBackend) -> Self {
+        self.config.watcher_backend = watcher_backend;
+        self
+    }
+
+    pub fn with_webhook(mut self, webhook: Webhook) -> Self {
+        self.config.webhooks.push(webhook);
+        self
+    }
+
+    /// Validates the configuration & creates the app, which starts watching on `App::run`
+    pub fn build(self) -> Result<App> {
+        let config = self.resolve_config()?;
+        App::new_with_extensions(config, self.extensions)
+    }
+
+    fn resolve_config(&self) -> Result<AppConfig> {
+        let mut config = self.config.clone();
+        config.repository = normalize_path(&config.repository).context(format!(
+            "Invalid repository path '{}'",
+            config.repository.display()
+        ))?;
+        if let Some(script_path) = &config.commit_message_script {
+            let script_path = config.repository.join(script_path);
+            config.commit_message_script = Some(normalize_path(&script_path).context(format!(
+                "Invalid commit message script path '{}'",
+                script_path.display()
+            ))?);
+        }
+        if self.extensions.commit_message_provider.is_some() {
+            config.validate_settings()?;
+        } else {
+            config.validate()?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use testresult::TestResult;
+
+    use super::*;
+    use crate::{cli::CliOptions, test_support::constants::TEST_COMMIT_MESSAGE};
+
+    #[test]
+    fn test_cli_defaults() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let repository = temp_dir.path().to_str().unwrap();
+
+        let config = AppBuilder::new(repository)
+            .with_commit_message(TEST_COMMIT_MESSAGE)
+            .resolve_config()?;
+        let cli_config = AppConfig::new(CliOptions::parse_from([
+            "gitwatch",
+            repository,
+            "--commit-message",
+            TEST_COMMIT_MESSAGE,
+        ]))?;
+        assert_eq!(config, cli_config);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_message_script() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        std::fs::write(temp_dir.path().join("message.sh"), "echo test")?;
+
+        let config = AppBuilder::new(temp_dir.path())
+            .with_commit_message(TEST_COMMIT_MESSAGE)
+            .with_commit_message_script("message.sh")
+            .resolve_config()?;
+        assert_eq!(config.commit_message, None);
+        assert_eq!(
+            config.commit_message_script,
+            Some(temp_dir.path().canonicalize()?.join("message.sh"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_config() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let err = AppBuilder::new(temp_dir.path()).build().err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Either commit-message or commit-message-script must be set"
+        );
+
+        let err = AppBuilder::new("/nonexistent/path").build().err().unwrap();
+        assert!(err.to_string().starts_with("Invalid repository path"));
+        Ok(())
+    }
+}
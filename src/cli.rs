@@ -1,10 +1,18 @@
-use std::path::PathBuf;
+use std::{net::SocketAddr, path::PathBuf};
 
 use clap::{Parser, ValueEnum};
 use clap_complete::Shell;
 use log::LevelFilter;
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    branch::{BranchSwitchPolicy, DetachedHeadPolicy},
+    control::ControlRequest,
+    log_file::LogRotation,
+    service::ServiceCommand,
+    watcher::{CommitMode, WatcherBackend},
+};
 
 #[derive(Parser)]
 #[command(
@@ -19,7 +27,43 @@ pub struct Cli {
 #[derive(Parser)]
 pub enum Commands {
     /// Watch a repository and commit changes
-    Watch(CliOptions),
+    Watch(Box<CliOptions>),
+
+    /// Control a running gitwatch instance
+    Ctl {
+        /// Path to the watched repository
+        repository: PathBuf,
+
+        #[command(subcommand)]
+        request: ControlRequest,
+    },
+
+    /// Show whether gitwatch is running for a repository and what it has done
+    Status {
+        /// Path to the repository
+        #[clap(default_value = ".")]
+        repository: PathBuf,
+
+        /// Print the status as JSON
+        #[clap(long)]
+        json: bool,
+    },
+
+    /// Answer git's fsmonitor hook from the gitwatch instance watching the current repository,
+    /// enable it via `git config core.fsmonitor "gitwatch fsmonitor"`
+    Fsmonitor {
+        /// Version of the hook protocol, only 2 is supported
+        version: u32,
+
+        /// Token of git's previous query
+        token: Option<String>,
+    },
+
+    /// Manage a systemd user service watching a repository
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
 
     /// Generate shell completion scripts
     Completion {
@@ -29,15 +73,34 @@ pub enum Commands {
     },
 }
 
-#[derive(Parser)]
+#[derive(Clone, Parser)]
 pub struct CliOptions {
     /// Path to the Git repository to monitor for changes
     #[clap(default_value = ".")]
     pub repository: PathBuf,
 
+    #[clap(flatten)]
+    pub branch: BranchOptions,
+
+    /// Number of seconds between commits in the interval & combined commit modes
+    #[clap(long = "commit-interval-seconds", default_value = "300")]
+    pub commit_interval_seconds: u64,
+
     #[clap(flatten)]
     pub commit_message: CommitMessageOptions,
 
+    /// When to commit changes:
+    /// debounce (once no changes were detected for the debounce time),
+    /// interval (every commit interval if anything changed, without watching for events) or
+    /// combined (after the debounce time, but at least every commit interval).
+    #[clap(
+        long = "commit-mode",
+        value_enum,
+        default_value_t = CommitMode::Debounce,
+        verbatim_doc_comment
+    )]
+    pub commit_mode: CommitMode,
+
     /// Automatically commit any existing changes on start
     #[clap(long = "commit-on-start", default_value = "true")]
     pub commit_on_start: std::primitive::bool,
@@ -51,6 +114,9 @@ pub struct CliOptions {
     #[clap(long = "dry-run", default_value = "false")]
     pub dry_run: bool,
 
+    #[clap(flatten)]
+    pub event_hooks: EventHookOptions,
+
     /// Regular expression pattern for files to exclude from watching.
     /// Matching is performed against repository-relative file paths.
     /// Note: the .git folder & gitignored files are ignored by default.
@@ -58,20 +124,79 @@ pub struct CliOptions {
     #[clap(short = 'i', long = "ignore-regex", verbatim_doc_comment)]
     pub ignore_regex: Option<Regex>,
 
+    #[clap(flatten)]
+    pub log_file: LogFileOptions,
+
+    /// Format of log records:
+    /// text (human-readable, colored if the output is a terminal & NO_COLOR is not set),
+    /// json or logfmt (one record per line on stderr, with fields such as event & commit_id).
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = LogFormat::Text,
+        verbatim_doc_comment
+    )]
+    pub log_format: LogFormat,
+
     /// Set the log level
     #[arg(long, value_enum, default_value_t = LogLevel::Info)]
     pub log_level: LogLevel,
 
+    /// Maximum number of commits per hour.
+    /// Further changes are held back and committed once the limit allows it again.
+    #[clap(long = "max-commits-per-hour", verbatim_doc_comment)]
+    pub max_commits_per_hour: Option<u32>,
+
+    /// Serve Prometheus metrics at http://<ADDR>/metrics, e.g. 127.0.0.1:9184
+    #[clap(long = "metrics-addr", value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Minimum number of seconds between two commits.
+    /// Changes detected in between are held back and committed together.
+    #[clap(
+        long = "min-commit-interval-seconds",
+        default_value = "0",
+        verbatim_doc_comment
+    )]
+    pub min_commit_interval_seconds: u64,
+
+    /// Number of seconds between scans of the repository when using the poll watcher backend
+    #[clap(long = "poll-interval-seconds", default_value = "5")]
+    pub poll_interval_seconds: u64,
+
+    /// Number of seconds between scans of the whole working tree.
+    /// In between, only the paths of detected changes are staged, a scan catches changes without events.
+    /// Use 0 to scan the whole working tree for every commit.
+    #[clap(
+        long = "reconcile-interval-seconds",
+        default_value = "600",
+        verbatim_doc_comment
+    )]
+    pub reconcile_interval_seconds: u64,
+
     /// Name of the remote to push to (if specified).
     /// Example: "origin".
     #[clap(short = 'r', long = "remote", verbatim_doc_comment)]
     pub remote: Option<String>,
 
+    /// Shut down another gitwatch instance watching the repository instead of failing.
+    /// The other instance commits its pending changes before exiting.
+    #[clap(long = "replace", default_value = "false", verbatim_doc_comment)]
+    pub replace: bool,
+
     /// Number of retry attempts when errors occur.
     /// Use -1 for infinite retries.
     #[clap(long = "retries", default_value = "3", verbatim_doc_comment)]
     pub retries: i32,
 
+    /// Run the repository's git hooks (pre-commit, commit-msg, post-commit, pre-push).
+    /// A failing pre-commit, commit-msg or pre-push hook aborts the commit or push.
+    #[clap(long = "run-hooks", default_value = "false", verbatim_doc_comment)]
+    pub run_hooks: bool,
+
+    #[clap(flatten)]
+    pub stage: StageOptions,
+
     /// Enable continuous monitoring of filesystem changes.
     /// Set to false for one-time commit of current changes.
     #[clap(
@@ -81,6 +206,23 @@ pub struct CliOptions {
         verbatim_doc_comment
     )]
     pub watch: std::primitive::bool,
+
+    /// How to detect filesystem changes:
+    /// native (the platform's notification API, e.g. inotify) or
+    /// poll (periodically compare file contents, for network filesystems & container volumes).
+    /// Native falls back to polling if the inotify watch limit is reached.
+    #[clap(
+        long = "watcher-backend",
+        value_enum,
+        default_value_t = WatcherBackend::Native,
+        verbatim_doc_comment
+    )]
+    pub watcher_backend: WatcherBackend,
+
+    /// URL to POST events to as JSON, can be repeated.
+    /// Use the config file to select events, set headers, retries or the timeout.
+    #[clap(long = "webhook", value_name = "URL", verbatim_doc_comment)]
+    pub webhooks: Vec<String>,
 }
 
 #[derive(Clone, Debug, clap::Args)]
@@ -98,7 +240,167 @@ pub struct CommitMessageOptions {
     pub script: Option<PathBuf>,
 }
 
-#[derive(Copy, Clone, Debug, Default, Deserialize, ValueEnum)]
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct BranchOptions {
+    /// Only autocommit on these branches (comma-separated).
+    /// Example: "notes,wip".
+    #[clap(long = "allowed-branches", value_delimiter = ',', verbatim_doc_comment)]
+    pub allowed_branches: Vec<String>,
+
+    /// Never autocommit on these branches (comma-separated).
+    /// Example: "main,master".
+    #[clap(long = "denied-branches", value_delimiter = ',', verbatim_doc_comment)]
+    pub denied_branches: Vec<String>,
+
+    /// What to do when HEAD is detached:
+    /// skip (pause autocommits until a branch is checked out),
+    /// commit (commit, but don't push) or
+    /// error (report an error & pause until a branch is checked out).
+    #[clap(
+        long = "detached-head",
+        value_enum,
+        default_value_t = DetachedHeadPolicy::Skip,
+        verbatim_doc_comment
+    )]
+    pub detached_head: DetachedHeadPolicy,
+
+    /// What to do when the checked out branch changes while watching:
+    /// log (keep committing to the new branch) or
+    /// pause (until the initial branch is checked out again).
+    #[clap(
+        long = "on-branch-switch",
+        value_enum,
+        default_value_t = BranchSwitchPolicy::Log,
+        verbatim_doc_comment
+    )]
+    pub on_branch_switch: BranchSwitchPolicy,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct StageOptions {
+    /// Pause autocommits if a change deletes more than this number of files.
+    /// Resuming autocommits or requesting a commit confirms the deletions.
+    #[clap(long = "max-deletions", verbatim_doc_comment)]
+    pub max_deletions: Option<usize>,
+
+    /// Pause autocommits if a change deletes more than this percentage of tracked files.
+    /// Protects against e.g. an accidental `rm -r` or an unmounted directory.
+    #[clap(long = "max-deletion-percent", verbatim_doc_comment)]
+    pub max_deletion_percent: Option<f64>,
+
+    /// Stage deleted files
+    #[clap(long = "stage-deleted", default_value = "true")]
+    pub deleted: std::primitive::bool,
+
+    /// Stage modified files
+    #[clap(long = "stage-modified", default_value = "true")]
+    pub modified: std::primitive::bool,
+
+    /// Stage renamed files
+    #[clap(long = "stage-renamed", default_value = "true")]
+    pub renamed: std::primitive::bool,
+
+    /// Stage untracked files.
+    /// If disabled, new files have to be added manually via `git add`.
+    #[clap(long = "stage-untracked", default_value = "true", verbatim_doc_comment)]
+    pub untracked: std::primitive::bool,
+}
+
+impl Default for StageOptions {
+    fn default() -> Self {
+        Self {
+            max_deletions: None,
+            max_deletion_percent: None,
+            deleted: true,
+            modified: true,
+            renamed: true,
+            untracked: true,
+        }
+    }
+}
+
+/// Shell commands run in the repository on events.
+/// The event is passed as JSON on stdin & as environment variables,
+/// e.g. GITWATCH_EVENT, GITWATCH_COMMIT_ID, GITWATCH_MESSAGE, GITWATCH_FILES or GITWATCH_ERROR.
+#[derive(Clone, Debug, Default, clap::Args)]
+pub struct EventHookOptions {
+    /// Command to run after changes were committed
+    #[clap(long = "on-commit", value_name = "COMMAND")]
+    pub on_commit: Option<String>,
+
+    /// Command to run if committing failed after all retries, or HEAD is detached with --detached-head=error
+    #[clap(long = "on-error", value_name = "COMMAND")]
+    pub on_error: Option<String>,
+
+    /// Command to run if autocommits were paused, manually or e.g. by a mass deletion or a rebase in progress
+    #[clap(long = "on-pause", value_name = "COMMAND")]
+    pub on_pause: Option<String>,
+
+    /// Command to run after commits were pushed
+    #[clap(long = "on-push", value_name = "COMMAND")]
+    pub on_push: Option<String>,
+
+    /// Command to run if pushing failed
+    #[clap(long = "on-push-failure", value_name = "COMMAND")]
+    pub on_push_failure: Option<String>,
+}
+
+#[derive(Clone, Debug, clap::Args)]
+pub struct LogFileOptions {
+    /// Also write logs to this file, e.g. to keep a history when running in the background.
+    /// The path can be absolute or relative to the repository.
+    /// Inside the repository, the file & its rotated files have to be ignored, e.g. via .gitignore or by placing it in .git/.
+    #[clap(long = "log-file", verbatim_doc_comment)]
+    pub path: Option<PathBuf>,
+
+    /// Log level of the log file, defaults to the --log-level
+    #[clap(long = "log-file-level", value_enum)]
+    pub level: Option<LogLevel>,
+
+    /// Maximum size of the log file in megabytes, when using size rotation
+    #[clap(long = "log-max-size-mb", default_value = "10")]
+    pub max_size_mb: u64,
+
+    /// Number of rotated log files to keep
+    #[clap(long = "log-retention", default_value = "7")]
+    pub retention: usize,
+
+    /// When to rotate the log file:
+    /// daily (rotated files are named after the day, e.g. gitwatch.log.2025-01-31),
+    /// size (once it exceeds the max size, rotated files are numbered, e.g. gitwatch.log.1) or
+    /// never.
+    #[clap(
+        long = "log-rotation",
+        value_enum,
+        default_value_t = LogRotation::Daily,
+        verbatim_doc_comment
+    )]
+    pub rotation: LogRotation,
+}
+
+impl Default for LogFileOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            level: None,
+            max_size_mb: 10,
+            retention: 7,
+            rotation: LogRotation::Daily,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+    Logfmt,
+}
+
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
 pub enum LogLevel {
     Trace,
     Debug,
@@ -1,7 +1,249 @@
 use anyhow::{bail, Context, Result};
-use log::debug;
-use std::path::Path;
+use git2::{DiffFormat, Repository, Status};
+use log::{debug, warn};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::SystemTime;
+
+/// Generates the message of an autocommit from the staged changes.
+/// Library users can implement it to supply their own messages, see `AppBuilder::with_commit_message_provider`.
+pub trait CommitMessageProvider: Send + Sync {
+    fn commit_message(&self, changes: &ChangeSet) -> Result<String>;
+
+    /// Whether `commit_message` reads `ChangeSet::changes`.
+    /// If not, the staged files are not collected and `changes` is empty.
+    fn reads_changes(&self) -> bool {
+        true
+    }
+}
+
+/// Changes staged for the next autocommit
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeSet {
+    /// None if HEAD is detached
+    pub branch: Option<String>,
+    pub changes: Vec<Change>,
+    pub repository: PathBuf,
+}
+
+/// Staged change of a single file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// Relative to the repository, the new path for renamed files
+    pub path: String,
+    pub status: ChangeStatus,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChangeStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    TypeChange,
+}
+
+impl ChangeStatus {
+    // None for entries without staged changes
+    pub(crate) fn from_index_status(status: Status) -> Option<Self> {
+        if status.is_index_new() {
+            Some(Self::Added)
+        } else if status.is_index_deleted() {
+            Some(Self::Deleted)
+        } else if status.is_index_modified() {
+            Some(Self::Modified)
+        } else if status.is_index_renamed() {
+            Some(Self::Renamed)
+        } else if status.is_index_typechange() {
+            Some(Self::TypeChange)
+        } else {
+            None
+        }
+    }
+}
+
+impl ChangeSet {
+    pub fn paths(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .map(|change| change.path.as_str())
+            .collect()
+    }
+
+    pub fn count(&self, status: ChangeStatus) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.status == status)
+            .count()
+    }
+
+    /// Patch of the staged changes against HEAD, like `git diff --cached`
+    pub fn diff(&self) -> Result<String> {
+        let repo = Repository::open(&self.repository)?;
+        // an unborn branch has no tree to compare with
+        let head_tree = match repo.head() {
+            Ok(head) => Some(head.peel_to_tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), None, None)?;
+
+        let mut patch = String::new();
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })?;
+        Ok(patch)
+    }
+}
+
+/// The same message for every commit
+pub struct StaticMessage(pub String);
+
+impl CommitMessageProvider for StaticMessage {
+    fn commit_message(&self, _changes: &ChangeSet) -> Result<String> {
+        Ok(self.0.clone())
+    }
+
+    fn reads_changes(&self) -> bool {
+        false
+    }
+}
+
+/// Message with placeholders for the changes, e.g. "Update {files} on {branch}".
+///
+/// Placeholders: `{branch}`, `{count}`, `{files}` (comma-separated), `{added}`, `{deleted}`,
+/// `{modified}`, `{renamed}` (number of files) and `{timestamp}` (RFC 3339, UTC).
+/// `{{` and `}}` are literal braces.
+/// Only available to library users, see `AppBuilder::with_commit_message_provider`.
+pub struct TemplateMessage {
+    template: String,
+}
+
+const TEMPLATE_PLACEHOLDERS: [&str; 8] = [
+    "added",
+    "branch",
+    "count",
+    "deleted",
+    "files",
+    "modified",
+    "renamed",
+    "timestamp",
+];
+
+impl TemplateMessage {
+    /// Fails for unknown placeholders & unbalanced braces
+    pub fn new(template: impl Into<String>) -> Result<Self> {
+        let template = template.into();
+        render_template(&template, |placeholder| {
+            if TEMPLATE_PLACEHOLDERS.contains(&placeholder) {
+                Ok(String::new())
+            } else {
+                bail!("Unknown placeholder '{{{placeholder}}}' in commit message template")
+            }
+        })?;
+        Ok(Self { template })
+    }
+}
+
+impl CommitMessageProvider for TemplateMessage {
+    fn commit_message(&self, changes: &ChangeSet) -> Result<String> {
+        render_template(&self.template, |placeholder| {
+            Ok(match placeholder {
+                "added" => changes.count(ChangeStatus::Added).to_string(),
+                "branch" => changes.branch.clone().unwrap_or_else(|| "HEAD".to_string()),
+                "count" => changes.changes.len().to_string(),
+                "deleted" => changes.count(ChangeStatus::Deleted).to_string(),
+                "files" => changes.paths().join(", "),
+                "modified" => changes.count(ChangeStatus::Modified).to_string(),
+                "renamed" => changes.count(ChangeStatus::Renamed).to_string(),
+                "timestamp" => humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+                _ => bail!("Unknown placeholder '{{{placeholder}}}' in commit message template"),
+            })
+        })
+    }
+}
+
+fn render_template<F>(template: &str, mut replace: F) -> Result<String>
+where
+    F: FnMut(&str) -> Result<String>,
+{
+    let mut rendered = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rendered.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                rendered.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => bail!("Unclosed '{{' in commit message template"),
+                    }
+                }
+                rendered.push_str(&replace(&placeholder)?);
+            }
+            '}' => {
+                bail!("Unmatched '}}' in commit message template, use '}}}}' for a literal brace")
+            }
+            c => rendered.push(c),
+        }
+    }
+    Ok(rendered)
+}
+
+/// Message printed by an executable script, which runs in the repository
+pub struct ScriptMessage(pub PathBuf);
+
+impl CommitMessageProvider for ScriptMessage {
+    fn commit_message(&self, changes: &ChangeSet) -> Result<String> {
+        generate_commit_message(&self.0, &changes.repository)
+    }
+
+    // the script inspects the repository on its own
+    fn reads_changes(&self) -> bool {
+        false
+    }
+}
+
+/// Tries the providers in order and uses the first message that could be generated,
+/// e.g. a static message as fallback for a script.
+/// Only available to library users, see `AppBuilder::with_commit_message_provider`.
+pub struct FallbackChain(pub Vec<Box<dyn CommitMessageProvider>>);
+
+impl CommitMessageProvider for FallbackChain {
+    fn commit_message(&self, changes: &ChangeSet) -> Result<String> {
+        let mut last_error = None;
+        for provider in &self.0 {
+            match provider.commit_message(changes) {
+                Ok(message) if !message.trim().is_empty() => return Ok(message),
+                Ok(_) => warn!("Generated commit message is empty, trying the next provider"),
+                Err(e) => {
+                    warn!("{e:#}, trying the next provider");
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) => Err(e.context("All commit message providers failed")),
+            None => bail!("All commit message providers failed"),
+        }
+    }
+
+    fn reads_changes(&self) -> bool {
+        self.0.iter().any(|provider| provider.reads_changes())
+    }
+}
 
 pub fn generate_commit_message(script_path: &Path, repo_path: &Path) -> Result<String> {
     let file_name = script_path
@@ -137,6 +379,112 @@ mod tests {
         Ok(())
     }
 
+    fn test_change_set() -> ChangeSet {
+        ChangeSet {
+            branch: Some("main".to_string()),
+            changes: vec![
+                Change {
+                    path: "notes/a.md".to_string(),
+                    status: ChangeStatus::Added,
+                },
+                Change {
+                    path: "b.md".to_string(),
+                    status: ChangeStatus::Modified,
+                },
+            ],
+            repository: PathBuf::from("/notes"),
+        }
+    }
+
+    #[test]
+    fn test_template_message() -> TestResult {
+        let template = TemplateMessage::new(
+            "Update {files} on {branch} ({count} files, {added} added, {deleted} deleted) {{x}}",
+        )?;
+        assert_eq!(
+            template.commit_message(&test_change_set())?,
+            "Update notes/a.md, b.md on main (2 files, 1 added, 0 deleted) {x}"
+        );
+
+        for (invalid_template, expected_error) in [
+            (
+                "Update {file}",
+                "Unknown placeholder '{file}' in commit message template",
+            ),
+            ("Update {files", "Unclosed '{' in commit message template"),
+            (
+                "Update files}",
+                "Unmatched '}' in commit message template, use '}}' for a literal brace",
+            ),
+        ] {
+            let err = TemplateMessage::new(invalid_template).err().unwrap();
+            assert_eq!(err.to_string(), expected_error);
+        }
+        Ok(())
+    }
+
+    struct FailingMessage;
+
+    impl CommitMessageProvider for FailingMessage {
+        fn commit_message(&self, _changes: &ChangeSet) -> Result<String> {
+            bail!("no message")
+        }
+    }
+
+    #[test]
+    fn test_fallback_chain() -> TestResult {
+        let chain = FallbackChain(vec![
+            Box::new(FailingMessage),
+            Box::new(StaticMessage(String::new())),
+            Box::new(StaticMessage("Fallback".to_string())),
+        ]);
+        assert_eq!(chain.commit_message(&test_change_set())?, "Fallback");
+
+        let chain = FallbackChain(vec![Box::new(FailingMessage)]);
+        let err = chain.commit_message(&test_change_set()).unwrap_err();
+        assert_eq!(
+            format!("{err:#}"),
+            "All commit message providers failed: no message"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reads_changes() -> TestResult {
+        assert!(!StaticMessage(String::new()).reads_changes());
+        assert!(!ScriptMessage(PathBuf::from("message.sh")).reads_changes());
+        assert!(TemplateMessage::new("Update {files}")?.reads_changes());
+        assert!(FailingMessage.reads_changes());
+
+        let chain = FallbackChain(vec![
+            Box::new(StaticMessage(String::new())),
+            Box::new(TemplateMessage::new("Update {files}")?),
+        ]);
+        assert!(chain.reads_changes());
+        let chain = FallbackChain(vec![Box::new(StaticMessage(String::new()))]);
+        assert!(!chain.reads_changes());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        std::fs::write(temp_dir.path().join("a.md"), "first line\n")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("a.md"))?;
+        index.write()?;
+
+        let change_set = ChangeSet {
+            repository: temp_dir.path().to_path_buf(),
+            ..test_change_set()
+        };
+        let diff = change_set.diff()?;
+        assert!(diff.contains("+++ b/a.md\n"), "Unexpected diff: {diff}");
+        assert!(diff.ends_with("\n+first line\n"), "Unexpected diff: {diff}");
+        Ok(())
+    }
+
     fn create_test_script(dir: &TempDir, content: &str) -> Result<std::path::PathBuf> {
         let script_path = dir.path().join("test_script.sh");
         OpenOptions::new()
@@ -1,4 +1,7 @@
-use std::path::{Path, PathBuf};
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
 use figment::{
@@ -10,18 +13,66 @@ use log::debug;
 use regex::Regex;
 use serde::Deserialize;
 
+use crate::{
+    branch::{BranchSwitchPolicy, DetachedHeadPolicy},
+    cli::LogLevel,
+    log_file::LogRotation,
+    watcher::{CommitMode, WatcherBackend},
+    webhook::Webhook,
+};
+
 #[derive(Debug, Deserialize, Default)]
 pub struct ConfigFile {
+    pub allowed_branches: Option<Vec<String>>,
+    pub commit_interval_seconds: Option<u64>,
     pub commit_message: Option<String>,
     pub commit_message_script: Option<PathBuf>,
+    pub commit_mode: Option<CommitMode>,
     pub commit_on_start: Option<bool>,
     pub debounce_seconds: Option<u64>,
+    pub denied_branches: Option<Vec<String>>,
+    pub detached_head: Option<DetachedHeadPolicy>,
     pub dry_run: Option<bool>,
     #[serde(default, with = "serde_regex")]
     pub ignore_regex: Option<Regex>,
+    pub log_file: Option<LogFileConfig>,
+    pub max_commits_per_hour: Option<u32>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub min_commit_interval_seconds: Option<u64>,
+    pub on_branch_switch: Option<BranchSwitchPolicy>,
+    pub on_commit: Option<String>,
+    pub on_error: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_push: Option<String>,
+    pub on_push_failure: Option<String>,
+    pub poll_interval_seconds: Option<u64>,
+    pub reconcile_interval_seconds: Option<u64>,
     pub remote: Option<String>,
     pub retries: Option<i32>,
+    pub run_hooks: Option<bool>,
+    pub stage: Option<StageConfig>,
     pub watch: Option<bool>,
+    pub watcher_backend: Option<WatcherBackend>,
+    pub webhooks: Option<Vec<Webhook>>,
+}
+
+#[derive(Debug, Deserialize, Default, PartialEq)]
+pub struct LogFileConfig {
+    pub level: Option<LogLevel>,
+    pub max_size_mb: Option<u64>,
+    pub path: Option<PathBuf>,
+    pub retention: Option<usize>,
+    pub rotation: Option<LogRotation>,
+}
+
+#[derive(Debug, Deserialize, Default, PartialEq)]
+pub struct StageConfig {
+    pub deleted: Option<bool>,
+    pub max_deletions: Option<usize>,
+    pub max_deletion_percent: Option<f64>,
+    pub modified: Option<bool>,
+    pub renamed: Option<bool>,
+    pub untracked: Option<bool>,
 }
 
 impl ConfigFile {
@@ -42,6 +93,7 @@ impl ConfigFile {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::events::EventKind;
     use std::{fs, path::PathBuf};
     use tempfile::TempDir;
     use testresult::TestResult;
@@ -50,13 +102,34 @@ mod tests {
         fn eq(&self, other: &Self) -> bool {
             self.ignore_regex.as_ref().map(|r| r.as_str())
                 == other.ignore_regex.as_ref().map(|r| r.as_str())
+                && self.allowed_branches == other.allowed_branches
+                && self.commit_interval_seconds == other.commit_interval_seconds
                 && self.commit_message == other.commit_message
+                && self.commit_mode == other.commit_mode
                 && self.commit_message_script == other.commit_message_script
                 && self.debounce_seconds == other.debounce_seconds
+                && self.denied_branches == other.denied_branches
+                && self.detached_head == other.detached_head
                 && self.dry_run == other.dry_run
+                && self.log_file == other.log_file
+                && self.max_commits_per_hour == other.max_commits_per_hour
+                && self.metrics_addr == other.metrics_addr
+                && self.min_commit_interval_seconds == other.min_commit_interval_seconds
+                && self.on_branch_switch == other.on_branch_switch
+                && self.on_commit == other.on_commit
+                && self.on_error == other.on_error
+                && self.on_pause == other.on_pause
+                && self.on_push == other.on_push
+                && self.on_push_failure == other.on_push_failure
+                && self.poll_interval_seconds == other.poll_interval_seconds
+                && self.reconcile_interval_seconds == other.reconcile_interval_seconds
                 && self.retries == other.retries
+                && self.run_hooks == other.run_hooks
+                && self.stage == other.stage
                 && self.commit_on_start == other.commit_on_start
                 && self.watch == other.watch
+                && self.watcher_backend == other.watcher_backend
+                && self.webhooks == other.webhooks
         }
     }
 
@@ -70,33 +143,108 @@ mod tests {
         let temp_dir = TempDir::new()?;
 
         let config_content = r#"
+        allowed_branches: ["notes"]
+        commit_interval_seconds: 600
         commit_message: "test commit"
         commit_message_script: "script.sh"
+        commit_mode: "combined"
         commit_on_start: true
         debounce_seconds: 5
+        denied_branches: ["main"]
+        detached_head: "commit"
         dry_run: true
         ignore_regex: "test.*"
+        log_file:
+          path: ".git/gitwatch.log"
+          level: "debug"
+          rotation: "size"
+        max_commits_per_hour: 20
+        metrics_addr: "127.0.0.1:9184"
+        min_commit_interval_seconds: 60
+        on_branch_switch: "pause"
+        on_commit: "notify-send 'Committed'"
+        on_push_failure: "notify-send 'Push failed'"
+        poll_interval_seconds: 30
+        reconcile_interval_seconds: 1200
         remote: "origin"
         retries: 3
+        run_hooks: true
+        stage:
+          untracked: false
+          max_deletions: 10
+          max_deletion_percent: 50
         watch: true
+        watcher_backend: "poll"
+        webhooks:
+          - url: "https://example.com/hook"
+            events: ["commit", "push_failure"]
+            headers:
+              Authorization: "Bearer secret"
+            timeout_seconds: 5
         "#;
 
         create_config_file(&temp_dir, config_content)?;
 
         let config = ConfigFile::load(temp_dir.path())?;
 
+        assert_eq!(config.allowed_branches, Some(vec!["notes".to_string()]));
+        assert_eq!(config.commit_interval_seconds, Some(600));
         assert_eq!(config.commit_message, Some("test commit".to_string()));
         assert_eq!(
             config.commit_message_script,
             Some(PathBuf::from("script.sh"))
         );
+        assert_eq!(config.commit_mode, Some(CommitMode::Combined));
         assert_eq!(config.commit_on_start, Some(true));
         assert_eq!(config.debounce_seconds, Some(5));
+        assert_eq!(config.denied_branches, Some(vec!["main".to_string()]));
+        assert_eq!(config.detached_head, Some(DetachedHeadPolicy::Commit));
         assert_eq!(config.dry_run, Some(true));
         assert_eq!(config.ignore_regex.as_ref().unwrap().as_str(), "test.*");
+        assert_eq!(
+            config.log_file,
+            Some(LogFileConfig {
+                level: Some(LogLevel::Debug),
+                path: Some(PathBuf::from(".git/gitwatch.log")),
+                rotation: Some(LogRotation::Size),
+                ..LogFileConfig::default()
+            })
+        );
+        assert_eq!(config.max_commits_per_hour, Some(20));
+        assert_eq!(config.metrics_addr, Some("127.0.0.1:9184".parse()?));
+        assert_eq!(config.min_commit_interval_seconds, Some(60));
+        assert_eq!(config.on_branch_switch, Some(BranchSwitchPolicy::Pause));
+        assert_eq!(config.on_commit, Some("notify-send 'Committed'".to_string()));
+        assert_eq!(config.on_error, None);
+        assert_eq!(
+            config.on_push_failure,
+            Some("notify-send 'Push failed'".to_string())
+        );
+        assert_eq!(config.poll_interval_seconds, Some(30));
+        assert_eq!(config.reconcile_interval_seconds, Some(1200));
         assert_eq!(config.remote, Some("origin".to_string()));
         assert_eq!(config.retries, Some(3));
+        assert_eq!(config.run_hooks, Some(true));
+        assert_eq!(
+            config.stage,
+            Some(StageConfig {
+                untracked: Some(false),
+                max_deletions: Some(10),
+                max_deletion_percent: Some(50.0),
+                ..StageConfig::default()
+            })
+        );
         assert_eq!(config.watch, Some(true));
+        assert_eq!(config.watcher_backend, Some(WatcherBackend::Poll));
+        assert_eq!(
+            config.webhooks,
+            Some(vec![Webhook {
+                events: vec![EventKind::Commit, EventKind::PushFailure],
+                headers: [("Authorization".to_string(), "Bearer secret".to_string())].into(),
+                timeout_seconds: 5,
+                ..Webhook::new("https://example.com/hook".to_string())
+            }])
+        );
 
         Ok(())
     }
@@ -0,0 +1,417 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    os::unix::{
+        fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc},
+    thread,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fsmonitor::FsMonitorResponse,
+    state::{AppState, Status},
+};
+
+/// Commands to control a running watch loop
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    /// Commit all changes immediately, regardless of pause & rate limits
+    Commit,
+    /// Re-read the configuration file and restart watching
+    Reload,
+    /// Commit pending changes and stop watching
+    Shutdown,
+    /// Pause autocommits, or resume them if they are paused
+    TogglePause,
+    /// Commit the changes held back while paused, after resuming via the shared state
+    Wake,
+}
+
+/// Why the watch loop stopped
+#[derive(Debug, PartialEq)]
+pub enum StopReason {
+    Reload,
+    Shutdown,
+}
+
+/// Request sent to the control socket, one JSON object per line
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, Subcommand)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    /// Commit all changes now, even if paused or rate-limited
+    Commit,
+    /// Answer a query of git's fsmonitor hook, used by `gitwatch fsmonitor`
+    #[command(skip)]
+    #[serde(rename = "fsmonitor")]
+    FsMonitor { token: String },
+    /// Pause autocommits
+    Pause,
+    /// Reload the config file
+    Reload,
+    /// Resume autocommits
+    Resume,
+    /// Use a one-off message for the next commit
+    SetMessage {
+        /// The commit message
+        message: String,
+    },
+    /// Show the status of the running instance
+    Status,
+}
+
+/// Response of the control socket, one JSON object per line
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ControlResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fsmonitor: Option<FsMonitorResponse>,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+}
+
+/// Listens on the control socket of a repository, removes the socket when dropped
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Binds the control socket and handles requests in a background thread.
+    /// Expects the repository's `InstanceLock` to be held, which guards against other instances.
+    pub fn bind(
+        repository: &Path,
+        control_tx: Sender<ControlCommand>,
+        state: Arc<AppState>,
+    ) -> Result<Self> {
+        let path = socket_path(repository)?;
+        if path.exists() {
+            // left over from a process that didn't exit cleanly
+            fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        debug!("Listening on control socket {}", path.display());
+
+        thread::Builder::new()
+            .name("gitwatch control".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            let control_tx = control_tx.clone();
+                            let state = state.clone();
+                            thread::spawn(move || {
+                                if let Err(e) = handle_connection(stream, &control_tx, &state) {
+                                    warn!("Control socket connection failed: {e}");
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Failed to accept control socket connection: {e}"),
+                    }
+                }
+            })?;
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn handle_connection(
+    stream: UnixStream,
+    control_tx: &Sender<ControlCommand>,
+    state: &AppState,
+) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => handle_request(request, control_tx, state),
+            Err(e) => ControlResponse {
+                error: Some(format!("Invalid request: {e}")),
+                ..ControlResponse::default()
+            },
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn handle_request(
+    request: ControlRequest,
+    control_tx: &Sender<ControlCommand>,
+    state: &AppState,
+) -> ControlResponse {
+    debug!("Received control request {request:?}");
+    // requests that only change the shared state are applied directly
+    let command = match request {
+        ControlRequest::Commit => ControlCommand::Commit,
+        ControlRequest::Reload => ControlCommand::Reload,
+        ControlRequest::Pause | ControlRequest::Resume => {
+            state.set_paused(request == ControlRequest::Pause);
+            if request == ControlRequest::Resume {
+                state.confirm_mass_deletion();
+                // the app may be shutting down, the state is updated nonetheless
+                let _ = control_tx.send(ControlCommand::Wake);
+            }
+            return ControlResponse {
+                ok: true,
+                ..ControlResponse::default()
+            };
+        }
+        ControlRequest::SetMessage { message } => {
+            state.set_next_commit_message(Some(message));
+            return ControlResponse {
+                ok: true,
+                ..ControlResponse::default()
+            };
+        }
+        ControlRequest::FsMonitor { token } => {
+            return ControlResponse {
+                fsmonitor: Some(state.fsmonitor().query(&token)),
+                ok: true,
+                ..ControlResponse::default()
+            };
+        }
+        ControlRequest::Status => {
+            return ControlResponse {
+                ok: true,
+                status: Some(state.status()),
+                ..ControlResponse::default()
+            };
+        }
+    };
+
+    match control_tx.send(command) {
+        Ok(()) => ControlResponse {
+            ok: true,
+            ..ControlResponse::default()
+        },
+        Err(_) => ControlResponse {
+            error: Some("gitwatch is shutting down".to_string()),
+            ..ControlResponse::default()
+        },
+    }
+}
+
+/// Sends a request to the gitwatch instance watching `repository`
+pub fn send_request(repository: &Path, request: &ControlRequest) -> Result<ControlResponse> {
+    let path = socket_path(repository)?;
+    let stream = UnixStream::connect(&path).with_context(|| {
+        format!(
+            "Failed to connect to {}, is gitwatch running for this repository?",
+            path.display()
+        )
+    })?;
+    exchange(stream, request)
+}
+
+/// Returns the status of the gitwatch instance watching `repository`, None if it isn't running
+pub fn query_status(repository: &Path) -> Result<Option<Status>> {
+    let path = socket_path(repository)?;
+    let stream = match UnixStream::connect(&path) {
+        Ok(stream) => stream,
+        // a refused connection means the socket was left over by a crashed instance
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None)
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to connect to {}", path.display()))
+        }
+    };
+    Ok(exchange(stream, &ControlRequest::Status)?.status)
+}
+
+fn exchange(mut stream: UnixStream, request: &ControlRequest) -> Result<ControlResponse> {
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let response: ControlResponse =
+        serde_json::from_str(&line).context("Invalid response from control socket")?;
+    if let Some(error) = &response.error {
+        bail!("{error}");
+    }
+    Ok(response)
+}
+
+/// Returns the control socket path of a repository, located in
+/// `$XDG_RUNTIME_DIR/gitwatch/` or a private directory in the temp dir as fallback
+pub fn socket_path(repository: &Path) -> Result<PathBuf> {
+    let runtime_dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("gitwatch"),
+        // the shared temp dir is world-writable, so the directory is per uid
+        None => env::temp_dir().join(format!("gitwatch-{}", unsafe { libc::getuid() })),
+    };
+    create_private_dir(&runtime_dir)?;
+    Ok(runtime_dir.join(socket_name(repository)))
+}
+
+// other users must not be able to connect to or replace the socket,
+// e.g. via a directory created in advance in the shared temp dir
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != unsafe { libc::getuid() } {
+        bail!(
+            "Directory {} is not owned by the current user",
+            dir.display()
+        );
+    }
+    if metadata.mode() & 0o077 != 0 {
+        bail!(
+            "Directory {} is accessible by other users (mode {:o})",
+            dir.display(),
+            metadata.mode() & 0o777
+        );
+    }
+    Ok(())
+}
+
+// Unix socket paths are limited to ~100 bytes, so the full repository path is hashed
+fn socket_name(repository: &Path) -> String {
+    let name = repository
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let hash = fnv1a(repository.as_os_str().as_encoded_bytes());
+    format!("{name}-{hash:016x}.sock")
+}
+
+// stable across Rust versions, unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_socket_name() {
+        let name = socket_name(Path::new("/home/user/notes"));
+        assert!(name.starts_with("notes-") && name.ends_with(".sock"));
+        assert_eq!(name, socket_name(Path::new("/home/user/notes")));
+        assert_ne!(name, socket_name(Path::new("/home/other/notes")));
+    }
+
+    #[test]
+    fn test_create_private_dir() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let dir = temp_dir.path().join("gitwatch");
+        create_private_dir(&dir)?;
+        assert_eq!(fs::metadata(&dir)?.mode() & 0o777, 0o700);
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755))?;
+        let err = create_private_dir(&dir).unwrap_err().to_string();
+        assert!(
+            err.contains("accessible by other users"),
+            "Unexpected error message: {err}"
+        );
+
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(temp_dir.path(), &link)?;
+        assert!(create_private_dir(&link).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_request_serialization() -> TestResult {
+        assert_eq!(
+            serde_json::to_string(&ControlRequest::Pause)?,
+            r#"{"command":"pause"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ControlRequest>(r#"{"command":"set-message","message":"wip"}"#)?,
+            ControlRequest::SetMessage {
+                message: "wip".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&ControlRequest::FsMonitor {
+                token: String::new()
+            })?,
+            r#"{"command":"fsmonitor","token":""}"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_request() {
+        let (control_tx, control_rx) = mpsc::channel();
+        let state = AppState::new(PathBuf::from("/repo"));
+
+        let response = handle_request(ControlRequest::Commit, &control_tx, &state);
+        assert!(response.ok);
+        assert_eq!(control_rx.try_recv(), Ok(ControlCommand::Commit));
+
+        let response = handle_request(
+            ControlRequest::SetMessage {
+                message: "wip".to_string(),
+            },
+            &control_tx,
+            &state,
+        );
+        assert!(response.ok);
+        assert_eq!(state.next_commit_message(), Some("wip".to_string()));
+
+        let response = handle_request(ControlRequest::Status, &control_tx, &state);
+        let status = response.status.unwrap();
+        assert_eq!(status.next_commit_message, Some("wip".to_string()));
+        assert_eq!(status.repository, PathBuf::from("/repo"));
+
+        // git has to scan the working tree while it isn't watched
+        let response = handle_request(
+            ControlRequest::FsMonitor {
+                token: String::new(),
+            },
+            &control_tx,
+            &state,
+        );
+        assert_eq!(response.fsmonitor.unwrap().paths, None);
+
+        let response = handle_request(ControlRequest::Pause, &control_tx, &state);
+        assert!(response.ok);
+        assert!(state.is_paused());
+
+        let response = handle_request(ControlRequest::Resume, &control_tx, &state);
+        assert!(response.ok);
+        assert!(!state.is_paused());
+        assert_eq!(control_rx.try_recv(), Ok(ControlCommand::Wake));
+
+        drop(control_rx);
+        let response = handle_request(ControlRequest::Reload, &control_tx, &state);
+        assert!(!response.ok);
+    }
+}
@@ -0,0 +1,183 @@
+use std::{
+    collections::BTreeMap,
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+
+use crate::control::ControlCommand;
+
+/// Everything that wakes up the watch loop, apart from its timers
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// A raw filesystem event, debounced by the watch loop via `Timer::Debounce`
+    Changes(notify::Result<notify::Event>),
+    Control(ControlCommand),
+}
+
+/// The single channel the watch loop blocks on, so it only wakes up if something happens
+pub struct WatchChannel {
+    receiver: Receiver<WatchEvent>,
+    sender: Sender<WatchEvent>,
+}
+
+impl Default for WatchChannel {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { receiver, sender }
+    }
+}
+
+impl WatchChannel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sender(&self) -> Sender<WatchEvent> {
+        self.sender.clone()
+    }
+
+    /// Forwards the commands from `control_rx`, e.g. signals & the control socket, to the channel.
+    /// The forwarding thread stops once `control_rx` is disconnected or the channel is dropped.
+    pub fn forward_control(&self, control_rx: Receiver<ControlCommand>) -> Result<()> {
+        let sender = self.sender();
+        thread::Builder::new()
+            .name("gitwatch control".to_string())
+            .spawn(move || {
+                for command in control_rx {
+                    if sender.send(WatchEvent::Control(command)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .context("Failed to start the control thread")?;
+        Ok(())
+    }
+
+    /// Blocks until an event arrives, or returns None once `deadline` is reached
+    pub fn recv(&self, deadline: Option<Instant>) -> Option<WatchEvent> {
+        // the channel holds a sender itself, so it is never disconnected
+        match deadline {
+            Some(deadline) => self
+                .receiver
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                .ok(),
+            None => self.receiver.recv().ok(),
+        }
+    }
+}
+
+/// A deadline the watch loop wakes up for
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Timer {
+    /// Commit the changed paths once no filesystem events arrived for the debounce time
+    Debounce,
+    /// Commit all changes in interval & combined mode
+    IntervalCommit,
+    /// Retry committing held back changes once the rate limit allows it
+    Throttle,
+    /// Stage the whole working tree, to catch changes without filesystem events
+    Reconcile,
+    /// Ping the systemd watchdog
+    Watchdog,
+}
+
+/// The active timers of the watch loop, each timer is due at most once per `set`
+#[derive(Debug, Default)]
+pub struct Timers {
+    deadlines: BTreeMap<Timer, Instant>,
+}
+
+impl Timers {
+    /// Sets or replaces the deadline of `timer`
+    pub fn set(&mut self, timer: Timer, deadline: Instant) {
+        self.deadlines.insert(timer, deadline);
+    }
+
+    pub fn cancel(&mut self, timer: Timer) {
+        self.deadlines.remove(&timer);
+    }
+
+    pub fn is_set(&self, timer: Timer) -> bool {
+        self.deadlines.contains_key(&timer)
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.deadlines.values().min().copied()
+    }
+
+    /// Removes & returns the timers that are due at `now`
+    pub fn take_due(&mut self, now: Instant) -> Vec<Timer> {
+        let due = self
+            .deadlines
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(timer, _)| *timer)
+            .collect::<Vec<_>>();
+        for timer in &due {
+            self.deadlines.remove(timer);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_timers() {
+        let mut timers = Timers::default();
+        let start = Instant::now();
+        assert_eq!(timers.next_deadline(), None);
+
+        timers.set(Timer::IntervalCommit, start + Duration::from_secs(60));
+        timers.set(Timer::Watchdog, start + Duration::from_secs(10));
+        assert_eq!(
+            timers.next_deadline(),
+            Some(start + Duration::from_secs(10))
+        );
+        assert!(timers.take_due(start).is_empty());
+
+        // a timer fires once, until it is set again
+        assert_eq!(
+            timers.take_due(start + Duration::from_secs(10)),
+            vec![Timer::Watchdog]
+        );
+        assert!(!timers.is_set(Timer::Watchdog));
+        assert_eq!(
+            timers.next_deadline(),
+            Some(start + Duration::from_secs(60))
+        );
+
+        timers.cancel(Timer::IntervalCommit);
+        assert_eq!(timers.next_deadline(), None);
+    }
+
+    #[test]
+    fn test_watch_channel() -> TestResult {
+        let channel = WatchChannel::new();
+        let (control_tx, control_rx) = mpsc::channel();
+        channel.forward_control(control_rx)?;
+
+        // nothing happened until the deadline
+        let start = Instant::now();
+        assert!(channel
+            .recv(Some(start + Duration::from_millis(100)))
+            .is_none());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        control_tx.send(ControlCommand::Shutdown)?;
+        assert!(matches!(
+            channel.recv(None),
+            Some(WatchEvent::Control(ControlCommand::Shutdown))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    fmt,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use git2::Repository;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig, observer::GitwatchObserver, repo::get_current_branch, sd_notify,
+    webhook::Webhook,
+};
+
+// how long dropping the emitter waits for running hooks & webhook deliveries
+const DROP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lifecycle event of a watched repository
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Changes were committed
+    Commit {
+        commit_id: String,
+        files: Vec<String>,
+        message: String,
+    },
+    /// Commits were pushed to the remote
+    Push { remote: String },
+    /// Pushing to the remote failed, the commit was created nonetheless
+    PushFailure { error: String, remote: String },
+    /// Committing failed after all retries & gitwatch exits,
+    /// or autocommits were paused on a detached HEAD with the `error` policy & gitwatch keeps running
+    Error { error: String },
+    /// Autocommits were paused, manually (via a signal, the control socket or `AppState::set_paused`)
+    /// or e.g. by a mass deletion or a rebase in progress
+    Pause { reason: String },
+}
+
+/// Kind of an `Event`, e.g. to select the events a webhook receives
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Commit,
+    Push,
+    PushFailure,
+    Error,
+    Pause,
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Commit { .. } => EventKind::Commit,
+            Event::Push { .. } => EventKind::Push,
+            Event::PushFailure { .. } => EventKind::PushFailure,
+            Event::Error { .. } => EventKind::Error,
+            Event::Pause { .. } => EventKind::Pause,
+        }
+    }
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Commit => "commit",
+            EventKind::Push => "push",
+            EventKind::PushFailure => "push_failure",
+            EventKind::Error => "error",
+            EventKind::Pause => "pause",
+        }
+    }
+}
+
+/// Shell commands run on events
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EventHooks {
+    pub on_commit: Option<String>,
+    pub on_error: Option<String>,
+    pub on_pause: Option<String>,
+    pub on_push: Option<String>,
+    pub on_push_failure: Option<String>,
+}
+
+impl EventHooks {
+    fn get_command(&self, event_kind: EventKind) -> Option<&str> {
+        match event_kind {
+            EventKind::Commit => self.on_commit.as_deref(),
+            EventKind::Push => self.on_push.as_deref(),
+            EventKind::PushFailure => self.on_push_failure.as_deref(),
+            EventKind::Error => self.on_error.as_deref(),
+            EventKind::Pause => self.on_pause.as_deref(),
+        }
+    }
+}
+
+/// Passes the events of a repository to the observers and the configured event hooks & webhooks
+#[derive(Default)]
+pub struct EventEmitter {
+    hooks: EventHooks,
+    observers: Vec<Arc<dyn GitwatchObserver>>,
+    repository: PathBuf,
+    // hooks & webhook deliveries running in the background
+    running_tasks: Mutex<Vec<JoinHandle<()>>>,
+    webhooks: Vec<Webhook>,
+}
+
+impl EventEmitter {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            hooks: config.event_hooks.clone(),
+            observers: Vec::new(),
+            repository: config.repository.clone(),
+            running_tasks: Mutex::new(Vec::new()),
+            webhooks: config.webhooks.clone(),
+        }
+    }
+
+    pub fn with_observers(mut self, observers: Vec<Arc<dyn GitwatchObserver>>) -> Self {
+        self.observers = observers;
+        self
+    }
+
+    /// Passes `event` to the observers, then runs its hook & delivers it to the webhooks
+    /// in the background, failures are only logged
+    pub fn emit(&self, event: Event) {
+        for observer in &self.observers {
+            notify_observer(observer.as_ref(), &event);
+        }
+
+        let event_name = event.kind().name();
+        let command = self.hooks.get_command(event.kind());
+        let webhooks = self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.accepts(event.kind()))
+            .collect::<Vec<_>>();
+        if command.is_none() && webhooks.is_empty() {
+            return;
+        }
+
+        let payload = match get_payload(&event, &self.repository, self.get_branch()) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to serialize {event_name} event: {e:#}");
+                return;
+            }
+        };
+
+        if let Some(command) = command {
+            debug!("Running on_{event_name} hook");
+            match run_hook(command, event_name, payload.clone(), &self.repository) {
+                Ok(handle) => self.track(handle),
+                Err(e) => warn!("Failed to run on_{event_name} hook: {e:#}"),
+            }
+        }
+
+        for webhook in webhooks {
+            debug!("Delivering {event_name} event to {}", webhook.url);
+            let webhook = webhook.clone();
+            let payload = payload.clone();
+            self.track(thread::spawn(move || {
+                if let Err(e) = webhook.deliver(&payload) {
+                    warn!("{e:#}");
+                }
+            }));
+        }
+    }
+
+    /// Stops waiting for the running hooks & webhook deliveries when dropped,
+    /// e.g. when the emitter is replaced on reload while watching continues
+    pub fn detach(&self) {
+        self.running_tasks.lock().unwrap().clear();
+    }
+
+    fn track(&self, handle: JoinHandle<()>) {
+        let mut running_tasks = self.running_tasks.lock().unwrap();
+        running_tasks.retain(|handle| !handle.is_finished());
+        running_tasks.push(handle);
+    }
+
+    // hooks still running after `timeout` are detached, they are not killed
+    fn wait_for_tasks(&mut self, timeout: Duration) {
+        let running_tasks = self.running_tasks.get_mut().unwrap();
+        if running_tasks.iter().any(|handle| !handle.is_finished()) {
+            debug!("Waiting for event hooks & webhooks to finish");
+        }
+        let deadline = Instant::now() + timeout;
+        while running_tasks.iter().any(|handle| !handle.is_finished()) {
+            if Instant::now() >= deadline {
+                warn!(
+                    "Event hooks or webhooks still running after {}s, not waiting for them",
+                    timeout.as_secs()
+                );
+                break;
+            }
+            // systemd keeps expecting watchdog pings while shutting down
+            sd_notify::watchdog();
+            thread::sleep(Duration::from_millis(50));
+        }
+        for handle in running_tasks.drain(..) {
+            if handle.is_finished() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    // None if HEAD is detached
+    fn get_branch(&self) -> Option<String> {
+        let repo = Repository::open(&self.repository).ok()?;
+        get_current_branch(&repo).ok().flatten()
+    }
+}
+
+impl fmt::Debug for EventEmitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventEmitter")
+            .field("hooks", &self.hooks)
+            .field("observers", &self.observers.len())
+            .field("repository", &self.repository)
+            .field("webhooks", &self.webhooks)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for EventEmitter {
+    // e.g. the on_commit hook of the final commit on shutdown
+    fn drop(&mut self) {
+        self.wait_for_tasks(DROP_TIMEOUT);
+    }
+}
+
+fn notify_observer(observer: &dyn GitwatchObserver, event: &Event) {
+    match event {
+        Event::Commit {
+            commit_id,
+            files,
+            message,
+        } => observer.on_commit(commit_id, files, message),
+        Event::Push { remote } => observer.on_push(remote),
+        Event::PushFailure { error, remote } => observer.on_push_failure(remote, error),
+        Event::Error { error } => observer.on_error(error),
+        Event::Pause { reason } => observer.on_paused(reason),
+    }
+}
+
+// The event is passed as JSON on stdin & as GITWATCH_* environment variables
+fn run_hook(
+    command: &str,
+    event_name: &'static str,
+    payload: serde_json::Value,
+    repository: &Path,
+) -> Result<JoinHandle<()>> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(repository)
+        .envs(get_env_vars(&payload))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute '{command}'"))?;
+
+    // hooks may take a while, e.g. to rebuild a site, so they must not hold back commits
+    let mut stdin = child.stdin.take();
+    Ok(thread::spawn(move || {
+        let result = (|| -> Result<()> {
+            if let Some(stdin) = stdin.as_mut() {
+                match stdin.write_all(payload.to_string().as_bytes()) {
+                    // the hook may exit without consuming its input
+                    Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                    _ => {}
+                }
+            }
+            // closes stdin, so hooks that read from it don't block
+            drop(stdin);
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                bail!(
+                    "exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                );
+            }
+            Ok(())
+        })();
+        if let Err(e) = result {
+            warn!("on_{event_name} hook failed: {e:#}");
+        }
+    }))
+}
+
+fn get_payload(
+    event: &Event,
+    repository: &Path,
+    branch: Option<String>,
+) -> Result<serde_json::Value> {
+    let mut payload = serde_json::to_value(event)?;
+    if let Some(fields) = payload.as_object_mut() {
+        fields.insert("branch".to_string(), branch.into());
+        fields.insert(
+            "repository".to_string(),
+            repository.display().to_string().into(),
+        );
+    }
+    Ok(payload)
+}
+
+// e.g. GITWATCH_EVENT=commit, lists like the committed files are separated by newlines
+fn get_env_vars(payload: &serde_json::Value) -> Vec<(String, String)> {
+    let Some(fields) = payload.as_object() else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => value.clone(),
+                serde_json::Value::Array(values) => values
+                    .iter()
+                    .map(|value| value.as_str().map_or(value.to_string(), str::to_string))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                value => value.to_string(),
+            };
+            (format!("GITWATCH_{}", key.to_uppercase()), value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use testresult::TestResult;
+
+    use super::*;
+    use crate::test_support::http_stub::HttpStub;
+
+    fn commit_event() -> Event {
+        Event::Commit {
+            commit_id: "d81da57".to_string(),
+            files: vec!["a.md".to_string(), "b.md".to_string()],
+            message: "Update notes".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_payload() -> TestResult {
+        let payload = get_payload(
+            &commit_event(),
+            Path::new("/notes"),
+            Some("main".to_string()),
+        )?;
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "event": "commit",
+                "branch": "main",
+                "commit_id": "d81da57",
+                "files": ["a.md", "b.md"],
+                "message": "Update notes",
+                "repository": "/notes",
+            })
+        );
+
+        let mut env_vars = get_env_vars(&payload);
+        env_vars.sort();
+        assert_eq!(
+            env_vars,
+            [
+                ("GITWATCH_BRANCH", "main"),
+                ("GITWATCH_COMMIT_ID", "d81da57"),
+                ("GITWATCH_EVENT", "commit"),
+                ("GITWATCH_FILES", "a.md\nb.md"),
+                ("GITWATCH_MESSAGE", "Update notes"),
+                ("GITWATCH_REPOSITORY", "/notes"),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_emit() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let output_path = temp_dir.path().join("output");
+        let stub = HttpStub::start(&[])?;
+        let emitter = EventEmitter {
+            hooks: EventHooks {
+                on_commit: Some(format!(
+                    "(echo \"$GITWATCH_EVENT $GITWATCH_COMMIT_ID\"; cat) > {}",
+                    output_path.display()
+                )),
+                on_push: Some("exit 1".to_string()),
+                ..EventHooks::default()
+            },
+            observers: Vec::new(),
+            repository: temp_dir.path().to_path_buf(),
+            running_tasks: Mutex::new(Vec::new()),
+            webhooks: vec![Webhook {
+                events: vec![EventKind::Commit],
+                ..Webhook::new(stub.url.clone())
+            }],
+        };
+
+        // no hook configured
+        emitter.emit(Event::Pause {
+            reason: "rebase in progress".to_string(),
+        });
+        // failures are only logged
+        emitter.emit(Event::Push {
+            remote: "origin".to_string(),
+        });
+        emitter.emit(commit_event());
+
+        // waits for the hooks & webhooks to finish
+        drop(emitter);
+        // the temporary directory is not a git repository
+        let expected_payload = get_payload(&commit_event(), temp_dir.path(), None)?;
+        assert_eq!(
+            fs::read_to_string(&output_path)?,
+            format!("commit d81da57\n{expected_payload}")
+        );
+        // only the commit event is delivered
+        let request = stub.next_request()?;
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&request.body)?,
+            expected_payload
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_for_tasks_timeout() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let mut emitter = EventEmitter {
+            hooks: EventHooks {
+                on_commit: Some("sleep 5".to_string()),
+                ..EventHooks::default()
+            },
+            observers: Vec::new(),
+            repository: temp_dir.path().to_path_buf(),
+            running_tasks: Mutex::new(Vec::new()),
+            webhooks: Vec::new(),
+        };
+        emitter.emit(commit_event());
+
+        let start = Instant::now();
+        emitter.wait_for_tasks(Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(emitter.running_tasks.get_mut().unwrap().is_empty());
+
+        // a detached emitter doesn't wait at all, e.g. on reload
+        emitter.emit(commit_event());
+        emitter.detach();
+        let start = Instant::now();
+        drop(emitter);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        Ok(())
+    }
+}
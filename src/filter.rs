@@ -35,7 +35,11 @@ impl PathFilter {
         };
 
         if relative_path.starts_with(".git") {
-            return true;
+            // changes to these paths signal a branch switch or the start or end of a git
+            // operation like a rebase, so paused autocommits can resume once it is finished
+            return !GIT_STATE_PATHS.iter().any(|operation_path| {
+                relative_path.starts_with(Path::new(".git").join(operation_path))
+            });
         }
 
         if let Match::Ignore(_) = self
@@ -59,6 +63,16 @@ impl PathFilter {
     }
 }
 
+const GIT_STATE_PATHS: [&str; 7] = [
+    "BISECT_LOG",
+    "CHERRY_PICK_HEAD",
+    "HEAD",
+    "MERGE_HEAD",
+    "REVERT_HEAD",
+    "rebase-apply",
+    "rebase-merge",
+];
+
 fn build_gitignore(repo_path: &Path) -> Result<Gitignore> {
     let mut builder = GitignoreBuilder::new(repo_path);
     let gitignore_path = repo_path.join(".gitignore");
@@ -89,6 +103,7 @@ mod tests {
 
         // test ignored files
         assert!(path_filter.is_path_ignored(&repo_path.join(".git/config")));
+        assert!(path_filter.is_path_ignored(&repo_path.join(".git/index.lock")));
         assert!(path_filter.is_path_ignored(&repo_path.join("test.ignored")));
         assert!(path_filter.is_path_ignored(&repo_path.join("ignored_dir/file.txt")));
 
@@ -99,6 +114,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_git_state_paths() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let repo_path = temp_dir.path();
+        let path_filter = PathFilter::new(repo_path, None)?;
+
+        assert!(!path_filter.is_path_ignored(&repo_path.join(".git/HEAD")));
+        assert!(!path_filter.is_path_ignored(&repo_path.join(".git/MERGE_HEAD")));
+        assert!(path_filter.is_path_ignored(&repo_path.join(".git/ORIG_HEAD")));
+        assert!(!path_filter.is_path_ignored(&repo_path.join(".git/rebase-merge/done")));
+        assert!(path_filter.is_path_ignored(&repo_path.join(".git/refs/heads/main")));
+        // every `git status` refreshes the index
+        assert!(path_filter.is_path_ignored(&repo_path.join(".git/index")));
+
+        Ok(())
+    }
+
     #[test]
     fn test_ignore_regex() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -0,0 +1,381 @@
+use std::{
+    collections::{BTreeSet, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{Condvar, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::{debug, warn};
+use notify::{self, Event, EventKind};
+use serde::{Deserialize, Serialize};
+
+/// The version of git's fsmonitor hook protocol answered by `gitwatch fsmonitor`
+pub const HOOK_VERSION: u32 = 2;
+
+const TOKEN_PREFIX: &str = "gitwatch";
+const COOKIE_PREFIX: &str = "gitwatch-fsmonitor-cookie-";
+// how long a query waits for the events of the changes made before it
+const COOKIE_TIMEOUT: Duration = Duration::from_secs(1);
+// git gets a trivial response for tokens older than the journal
+const MAX_JOURNAL_ENTRIES: usize = 100_000;
+
+/// Answer to a query of git's fsmonitor hook
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FsMonitorResponse {
+    /// Paths changed since the queried token, None if git has to scan the whole working tree
+    pub paths: Option<Vec<String>>,
+    /// The token git passes to the next query
+    pub token: String,
+}
+
+impl FsMonitorResponse {
+    /// Formats the response as git expects it from a v2 hook, the token & paths separated by NUL
+    pub fn to_hook_output(&self) -> Vec<u8> {
+        let mut output = format!("{}\0", self.token).into_bytes();
+        match &self.paths {
+            Some(paths) => {
+                for path in paths {
+                    output.extend_from_slice(path.as_bytes());
+                    output.push(0);
+                }
+            }
+            None => output.extend_from_slice(b"/\0"),
+        }
+        output
+    }
+}
+
+/// Records the filesystem events of the watched working tree, so git commands can ask
+/// which paths changed since their last query instead of scanning the working tree.
+#[derive(Debug)]
+pub struct FsMonitor {
+    cookie_seen: Condvar,
+    // tokens of other processes are never trusted
+    instance: String,
+    journal: Mutex<Journal>,
+}
+
+#[derive(Debug, Default)]
+struct Journal {
+    // (seq, path relative to the working tree), oldest first
+    entries: VecDeque<(u64, String)>,
+    // bumped whenever events may have been missed, which invalidates all tokens
+    epoch: u64,
+    next_cookie: u64,
+    pending_cookies: HashSet<u64>,
+    // the working tree while its events are recorded
+    root: Option<PathBuf>,
+    seq: u64,
+    // the entries up to this seq were dropped
+    truncated_seq: u64,
+}
+
+impl Journal {
+    fn push(&mut self, path: String) {
+        self.seq += 1;
+        self.entries.push_back((self.seq, path));
+        if self.entries.len() > MAX_JOURNAL_ENTRIES {
+            if let Some((seq, _)) = self.entries.pop_front() {
+                self.truncated_seq = seq;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.epoch += 1;
+        self.entries.clear();
+        self.pending_cookies.clear();
+        self.truncated_seq = self.seq;
+    }
+
+    fn token(&self, instance: &str) -> String {
+        format!("{TOKEN_PREFIX}:{instance}:{}:{}", self.epoch, self.seq)
+    }
+
+    fn changes_since(&self, instance: &str, token: &str) -> Option<Vec<String>> {
+        let parts = token.split(':').collect::<Vec<_>>();
+        let [prefix, token_instance, epoch, seq] = parts.as_slice() else {
+            return None;
+        };
+        let seq = seq.parse::<u64>().ok()?;
+        if *prefix != TOKEN_PREFIX
+            || *token_instance != instance
+            || epoch.parse::<u64>().ok()? != self.epoch
+            || !(self.truncated_seq..=self.seq).contains(&seq)
+        {
+            return None;
+        }
+        let paths = self
+            .entries
+            .iter()
+            .filter(|(entry_seq, _)| *entry_seq > seq)
+            .map(|(_, path)| path.clone())
+            .collect::<BTreeSet<_>>();
+        Some(paths.into_iter().collect())
+    }
+}
+
+impl Default for FsMonitor {
+    fn default() -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            cookie_seen: Condvar::new(),
+            instance: format!("{}.{}", process::id(), started_at.as_nanos()),
+            journal: Mutex::new(Journal::default()),
+        }
+    }
+}
+
+impl FsMonitor {
+    /// Starts recording the events below `root`, tokens handed out before get a trivial response
+    pub fn start(&self, root: &Path) {
+        let mut journal = self.journal.lock().unwrap();
+        journal.reset();
+        journal.root = Some(root.to_path_buf());
+    }
+
+    pub fn stop(&self) {
+        let mut journal = self.journal.lock().unwrap();
+        journal.reset();
+        journal.root = None;
+        self.cookie_seen.notify_all();
+    }
+
+    /// Records a raw filesystem event, before it is debounced
+    pub fn record(&self, event: &notify::Result<Event>) {
+        let mut journal = self.journal.lock().unwrap();
+        let Some(root) = journal.root.clone() else {
+            return;
+        };
+        let event = match event {
+            // reads, e.g. by git itself, don't change anything
+            Ok(event) if matches!(event.kind, EventKind::Access(_)) => return,
+            Ok(event) if !event.need_rescan() => event,
+            // events may have been lost
+            _ => {
+                debug!("fsmonitor: events may have been lost, git has to rescan");
+                journal.reset();
+                self.cookie_seen.notify_all();
+                return;
+            }
+        };
+
+        for path in &event.paths {
+            let Ok(relative) = path.strip_prefix(&root) else {
+                continue;
+            };
+            if let Ok(git_path) = relative.strip_prefix(".git") {
+                if let Some(id) = cookie_id(git_path) {
+                    journal.pending_cookies.remove(&id);
+                    self.cookie_seen.notify_all();
+                }
+                continue;
+            }
+            match relative.to_str() {
+                Some(relative) if !relative.is_empty() => {
+                    journal.push(relative.to_string());
+                    // git invalidates everything below a path with a trailing slash,
+                    // a deleted path may have been a directory
+                    if !path.is_file() {
+                        journal.push(format!("{relative}/"));
+                    }
+                }
+                // the working tree itself changed or git can't be told about the path
+                _ => {
+                    journal.reset();
+                    self.cookie_seen.notify_all();
+                }
+            }
+        }
+    }
+
+    /// Answers a query of git's fsmonitor hook with the paths changed since `token`.
+    /// Waits until the events of changes made before the query were recorded.
+    pub fn query(&self, token: &str) -> FsMonitorResponse {
+        let root = self.journal.lock().unwrap().root.clone();
+        let synced = root.is_some_and(|root| self.sync(&root));
+
+        let journal = self.journal.lock().unwrap();
+        let paths = if synced {
+            journal.changes_since(&self.instance, token)
+        } else {
+            None
+        };
+        debug!(
+            "fsmonitor: answering query for token '{token}' with {}",
+            paths.as_ref().map_or("a full scan".to_string(), |paths| {
+                format!("{} changed paths", paths.len())
+            })
+        );
+        FsMonitorResponse {
+            paths,
+            token: journal.token(&self.instance),
+        }
+    }
+
+    // writes a cookie file & waits for its event, after which all earlier events were recorded
+    fn sync(&self, root: &Path) -> bool {
+        let git_dir = root.join(".git");
+        // the git dir of a linked worktree or submodule is not watched
+        if !git_dir.is_dir() {
+            return false;
+        }
+
+        let id = {
+            let mut journal = self.journal.lock().unwrap();
+            let id = journal.next_cookie;
+            journal.next_cookie += 1;
+            journal.pending_cookies.insert(id);
+            id
+        };
+        let cookie = git_dir.join(format!("{COOKIE_PREFIX}{id}"));
+        if let Err(e) = fs::write(&cookie, "") {
+            warn!("Failed to write fsmonitor cookie {}: {e}", cookie.display());
+            self.journal.lock().unwrap().pending_cookies.remove(&id);
+            return false;
+        }
+
+        let journal = self.journal.lock().unwrap();
+        let (mut journal, result) = self
+            .cookie_seen
+            .wait_timeout_while(journal, COOKIE_TIMEOUT, |journal| {
+                journal.pending_cookies.contains(&id)
+            })
+            .unwrap();
+        journal.pending_cookies.remove(&id);
+        drop(journal);
+        let _ = fs::remove_file(&cookie);
+        !result.timed_out()
+    }
+}
+
+fn cookie_id(git_path: &Path) -> Option<u64> {
+    git_path.to_str()?.strip_prefix(COOKIE_PREFIX)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{AccessKind, Flag};
+    use testresult::TestResult;
+
+    use super::*;
+
+    fn event(paths: &[&Path]) -> notify::Result<Event> {
+        Ok(paths
+            .iter()
+            .fold(Event::new(EventKind::Any), |event, path| {
+                event.add_path(path.to_path_buf())
+            }))
+    }
+
+    #[test]
+    fn test_hook_output() {
+        let response = FsMonitorResponse {
+            paths: Some(vec!["a.txt".to_string(), "dir/".to_string()]),
+            token: "gitwatch:1:1:2".to_string(),
+        };
+        assert_eq!(response.to_hook_output(), b"gitwatch:1:1:2\0a.txt\0dir/\0");
+
+        let response = FsMonitorResponse {
+            paths: None,
+            token: "gitwatch:1:1:2".to_string(),
+        };
+        assert_eq!(response.to_hook_output(), b"gitwatch:1:1:2\0/\0");
+    }
+
+    #[test]
+    fn test_journal() -> TestResult {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        fs::create_dir(root.join(".git"))?;
+        fs::write(root.join("a.txt"), "a")?;
+        let fsmonitor = FsMonitor::default();
+        let changes_since = |token: &str| {
+            fsmonitor
+                .journal
+                .lock()
+                .unwrap()
+                .changes_since(&fsmonitor.instance, token)
+        };
+        let token = || fsmonitor.journal.lock().unwrap().token(&fsmonitor.instance);
+
+        // nothing is recorded before watching
+        fsmonitor.record(&event(&[&root.join("a.txt")]));
+        assert!(fsmonitor.journal.lock().unwrap().entries.is_empty());
+
+        fsmonitor.start(root);
+        let start_token = token();
+        assert_eq!(changes_since(&start_token), Some(vec![]));
+        assert_eq!(changes_since(""), None);
+        assert_eq!(changes_since("builtin:1:2"), None);
+
+        fsmonitor.record(&event(&[
+            &root.join("a.txt"),
+            &root.join("deleted"),
+            &root.join(".git/index"),
+        ]));
+        let token_a = token();
+        fsmonitor.record(&event(&[&root.join("a.txt")]));
+        assert_eq!(
+            changes_since(&start_token),
+            Some(vec![
+                "a.txt".to_string(),
+                "deleted".to_string(),
+                "deleted/".to_string()
+            ])
+        );
+        assert_eq!(changes_since(&token_a), Some(vec!["a.txt".to_string()]));
+        assert_eq!(changes_since(&token()), Some(vec![]));
+
+        // reads are no changes
+        fsmonitor.record(&Ok(
+            Event::new(EventKind::Access(AccessKind::Any)).add_path(root.to_path_buf())
+        ));
+        assert_eq!(changes_since(&token()), Some(vec![]));
+
+        // tokens are invalidated once events may have been lost
+        let rescan = Event::new(EventKind::Other).set_flag(Flag::Rescan);
+        fsmonitor.record(&Ok(rescan));
+        assert_eq!(changes_since(&token_a), None);
+        assert_eq!(changes_since(&token()), Some(vec![]));
+
+        // and after restarting
+        let token_b = token();
+        fsmonitor.stop();
+        fsmonitor.start(root);
+        assert_eq!(changes_since(&token_b), None);
+
+        // a query without a watcher delivering the cookie event gets a trivial response
+        let response = fsmonitor.query(&token());
+        assert_eq!(response.paths, None);
+        assert!(!root.join(format!(".git/{COOKIE_PREFIX}0")).exists());
+
+        fsmonitor.stop();
+        assert_eq!(fsmonitor.query(&response.token).paths, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_journal() {
+        let fsmonitor = FsMonitor::default();
+        let mut journal = fsmonitor.journal.lock().unwrap();
+        journal.root = Some(PathBuf::from("/repo"));
+        let token = journal.token(&fsmonitor.instance);
+        for i in 0..=MAX_JOURNAL_ENTRIES {
+            journal.push(format!("file{i}"));
+        }
+        assert_eq!(journal.entries.len(), MAX_JOURNAL_ENTRIES);
+        assert_eq!(journal.changes_since(&fsmonitor.instance, &token), None);
+
+        let token = journal.token(&fsmonitor.instance);
+        journal.push("new".to_string());
+        assert_eq!(
+            journal.changes_since(&fsmonitor.instance, &token),
+            Some(vec!["new".to_string()])
+        );
+    }
+}
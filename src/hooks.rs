@@ -0,0 +1,202 @@
+use std::{
+    io::{ErrorKind, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+use log::{debug, trace};
+
+/// Runs the client-side git hooks of a repository.
+/// libgit2 does not execute hooks on its own, so gitwatch invokes them explicitly.
+pub struct GitHooks {
+    hooks_dir: PathBuf,
+    work_dir: PathBuf,
+}
+
+impl GitHooks {
+    pub fn new(repo: &Repository) -> Result<Self> {
+        let work_dir = repo
+            .workdir()
+            .context("Hooks are not supported in bare repositories")?
+            .to_path_buf();
+
+        // respect core.hooksPath, which is interpreted relative to the working directory
+        let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+            Ok(path) if path.is_relative() => work_dir.join(path),
+            Ok(path) => path,
+            Err(_) => repo.path().join("hooks"),
+        };
+
+        Ok(Self {
+            hooks_dir,
+            work_dir,
+        })
+    }
+
+    /// Executes the hook `name` if it exists and is executable.
+    /// Fails if the hook exits with a non-zero status.
+    pub fn run(&self, name: &str, args: &[&str], stdin: Option<&str>) -> Result<()> {
+        let hook_path = self.hooks_dir.join(name);
+        if !is_executable(&hook_path) {
+            trace!("Skipping {name} hook, {} not found", hook_path.display());
+            return Ok(());
+        }
+
+        debug!("Running {name} hook");
+        let mut child = Command::new(&hook_path)
+            .args(args)
+            .current_dir(&self.work_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to execute hook '{}'", hook_path.display()))?;
+
+        // dropping stdin closes it, so hooks that read from it don't block
+        if let (Some(mut child_stdin), Some(input)) = (child.stdin.take(), stdin) {
+            // the hook may exit without consuming its input
+            match child_stdin.write_all(input.as_bytes()) {
+                Err(e) if e.kind() != ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {}
+            }
+        }
+
+        let output = child.wait_with_output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !stdout.trim().is_empty() {
+            debug!("{name} hook output:\n{}", stdout.trim_end());
+        }
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            bail!(
+                "Hook '{name}' failed with exit code {}.\nError: {}",
+                output.status,
+                stderr
+            );
+        }
+        Ok(())
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Creates a shell script hook in `dir`, shared by the tests of the modules running hooks
+#[cfg(test)]
+pub(crate) fn create_hook(dir: &Path, name: &str, content: &str, mode: u32) -> Result<PathBuf> {
+    use std::{
+        fs::{self, OpenOptions},
+        os::unix::fs::OpenOptionsExt,
+    };
+
+    fs::create_dir_all(dir)?;
+    let hook_path = dir.join(name);
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(mode)
+        .open(&hook_path)?
+        .write_all(format!("#!/bin/sh\n{content}").as_bytes())?;
+    Ok(hook_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_missing_hook() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+
+        let hooks = GitHooks::new(&repo)?;
+        hooks.run("pre-commit", &[], None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_executable_hook() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        create_hook(&repo.path().join("hooks"), "pre-commit", "exit 1", 0o644)?;
+
+        let hooks = GitHooks::new(&repo)?;
+        hooks.run("pre-commit", &[], None)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_failing_hook() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        create_hook(
+            &repo.path().join("hooks"),
+            "pre-commit",
+            "echo 'lint failed' >&2; exit 1",
+            0o755,
+        )?;
+
+        let hooks = GitHooks::new(&repo)?;
+        let err = hooks.run("pre-commit", &[], None).unwrap_err().to_string();
+        assert!(
+            err.contains("Hook 'pre-commit' failed") && err.contains("lint failed"),
+            "Unexpected error message: {err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hook_args_and_stdin() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        create_hook(
+            &repo.path().join("hooks"),
+            "pre-push",
+            "echo \"$1 $2\" > args.txt; cat > stdin.txt",
+            0o755,
+        )?;
+
+        let hooks = GitHooks::new(&repo)?;
+        hooks.run("pre-push", &["origin", "url"], Some("refs/heads/main\n"))?;
+
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("args.txt"))?,
+            "origin url\n"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("stdin.txt"))?,
+            "refs/heads/main\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_core_hooks_path() -> TestResult {
+        let temp_dir = TempDir::new()?;
+        let repo = Repository::init(temp_dir.path())?;
+        repo.config()?.set_str("core.hooksPath", ".githooks")?;
+        create_hook(
+            &temp_dir.path().join(".githooks"),
+            "post-commit",
+            "exit 1",
+            0o755,
+        )?;
+
+        let hooks = GitHooks::new(&repo)?;
+        assert!(hooks.run("post-commit", &[], None).is_err());
+        Ok(())
+    }
+}
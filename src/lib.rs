@@ -1,17 +1,41 @@
 pub mod app;
 pub mod app_config;
+#[cfg(feature = "tokio")]
+pub mod async_api;
+pub mod branch;
+pub mod builder;
 pub mod cli;
 pub mod commit_message;
 pub mod config_file;
+pub mod control;
+pub mod event_loop;
+pub mod events;
 pub mod filter;
+pub mod fsmonitor;
+pub mod hooks;
+pub mod lock;
+pub mod log_file;
 pub mod logger;
+pub mod metrics;
+pub mod observer;
 pub mod repo;
+pub mod sd_notify;
+pub mod service;
+pub mod signals;
+pub mod stage;
+pub mod state;
+pub mod status;
+pub mod throttle;
 pub mod util;
 pub mod watcher;
+pub mod webhook;
 
 #[cfg(test)]
 pub mod test_support {
     pub mod constants {
         include!("../tests/support/constants.rs");
     }
+    pub mod http_stub {
+        include!("../tests/support/http_stub.rs");
+    }
 }
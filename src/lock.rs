@@ -0,0 +1,212 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, info};
+
+const LOCK_FILE_NAME: &str = "gitwatch.lock";
+// how long to wait for a replaced instance to commit its pending changes & exit
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Advisory lock that prevents multiple gitwatch instances from watching the same repository.
+/// The lock file contains the PID & hostname of its owner and is removed when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    // the lock is released when the file is closed
+    _file: File,
+    path: PathBuf,
+}
+
+// Process holding the lock, as recorded in the lock file
+#[derive(Debug, PartialEq)]
+struct LockOwner {
+    hostname: String,
+    pid: u32,
+}
+
+impl InstanceLock {
+    /// Locks the repository with the git directory `git_dir`.
+    /// Fails if another instance holds the lock, unless `replace` is set,
+    /// in which case the other instance is asked to shut down.
+    pub fn acquire(git_dir: &Path, replace: bool) -> Result<Self> {
+        let path = git_dir.join(LOCK_FILE_NAME);
+        if let Some(lock) = Self::try_acquire(&path)? {
+            return Ok(lock);
+        }
+
+        let owner = LockOwner::read(&path);
+        let owner_description = owner
+            .as_ref()
+            .map_or("unknown process".to_string(), |owner| owner.to_string());
+        if !replace {
+            bail!(
+                "gitwatch is already running for this repository ({owner_description}). Stop it or use --replace to take over"
+            );
+        }
+        let Some(owner) = owner else {
+            bail!("Cannot replace gitwatch, {} does not contain a PID", path.display());
+        };
+        owner.shut_down()?;
+
+        let deadline = Instant::now() + REPLACE_TIMEOUT;
+        while Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(100));
+            if let Some(lock) = Self::try_acquire(&path)? {
+                return Ok(lock);
+            }
+        }
+        bail!(
+            "gitwatch ({owner}) did not shut down within {}s",
+            REPLACE_TIMEOUT.as_secs()
+        );
+    }
+
+    fn try_acquire(path: &Path) -> Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+
+        // the previous owner removes the file on exit, possibly after we opened it
+        let is_removed = fs::metadata(path)
+            .map(|metadata| metadata.ino() != file.metadata().map_or(0, |m| m.ino()))
+            .unwrap_or(true);
+        if is_removed {
+            return Self::try_acquire(path);
+        }
+
+        file.set_len(0)?;
+        write!(&file, "{}\n{}\n", process::id(), hostname())?;
+        debug!("Acquired lock {}", path.display());
+        Ok(Some(Self {
+            _file: file,
+            path: path.to_path_buf(),
+        }))
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        // removed while still locked, see `try_acquire`
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl LockOwner {
+    fn read(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        let mut lines = content.lines();
+        let pid = lines.next()?.parse().ok()?;
+        let hostname = lines.next().unwrap_or_default().to_string();
+        Some(Self { hostname, pid })
+    }
+
+    // asks the owner to commit its pending changes & exit
+    fn shut_down(&self) -> Result<()> {
+        if self.hostname != hostname() {
+            bail!("Cannot replace gitwatch ({self}), it is running on another host");
+        }
+        info!("Asking gitwatch ({self}) to shut down");
+        let pid = i32::try_from(self.pid).context("Invalid PID")?;
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("Failed to stop gitwatch ({self})"));
+        }
+        Ok(())
+    }
+}
+
+impl Display for LockOwner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "PID {} on {}", self.pid, self.hostname)
+    }
+}
+
+fn hostname() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer is valid for writes of its length
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+        return "unknown".to_string();
+    }
+    let len = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_single_instance() -> TestResult {
+        let git_dir = tempfile::tempdir()?;
+        let lock_path = git_dir.path().join(LOCK_FILE_NAME);
+
+        let lock = InstanceLock::acquire(git_dir.path(), false)?;
+        assert_eq!(
+            LockOwner::read(&lock_path),
+            Some(LockOwner {
+                hostname: hostname(),
+                pid: process::id(),
+            })
+        );
+
+        // locks of separately opened files conflict even within the same process
+        let err = InstanceLock::acquire(git_dir.path(), false).unwrap_err();
+        assert!(
+            err.to_string().contains(&format!(
+                "gitwatch is already running for this repository (PID {}",
+                process::id()
+            )),
+            "Unexpected error: {err}"
+        );
+
+        drop(lock);
+        assert!(!lock_path.exists());
+        InstanceLock::acquire(git_dir.path(), false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_lock_file() -> TestResult {
+        let git_dir = tempfile::tempdir()?;
+        // left over by a crashed instance, which released the lock when exiting
+        fs::write(git_dir.path().join(LOCK_FILE_NAME), "1\nother-host\n")?;
+        InstanceLock::acquire(git_dir.path(), false)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_on_other_host() -> TestResult {
+        let git_dir = tempfile::tempdir()?;
+        let lock_path = git_dir.path().join(LOCK_FILE_NAME);
+        let _lock = InstanceLock::acquire(git_dir.path(), false)?;
+        fs::write(&lock_path, "1\nother-host\n")?;
+
+        let err = InstanceLock::acquire(git_dir.path(), true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot replace gitwatch (PID 1 on other-host), it is running on another host"
+        );
+        Ok(())
+    }
+}
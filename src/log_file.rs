@@ -0,0 +1,326 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    mem,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::LogLevel, state::unix_timestamp};
+
+/// Settings of the log file, in addition to stdout & stderr
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LogFile {
+    pub level: LogLevel,
+    pub max_size_mb: u64,
+    pub path: PathBuf,
+    // number of rotated files to keep
+    pub retention: usize,
+    pub rotation: LogRotation,
+}
+
+impl LogFile {
+    /// Returns true if `path` is the log file or one of its rotated files
+    pub fn is_log_path(&self, path: &Path) -> bool {
+        if path == self.path {
+            return true;
+        }
+        let (Some(file_name), Some(name)) = (self.path.file_name(), path.file_name()) else {
+            return false;
+        };
+        // rotated files of a previous rotation setting may still be around
+        path.parent() == self.path.parent()
+            && name
+                .to_string_lossy()
+                .strip_prefix(&format!("{}.", file_name.to_string_lossy()))
+                .is_some_and(|suffix| is_date(suffix) || is_number(suffix))
+    }
+
+    /// The log file & an example of its rotated files, e.g. to check that they are ignored
+    pub fn sample_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.path.clone()];
+        match self.rotation {
+            LogRotation::Daily => paths.push(rotated_path(&self.path, "2025-01-31")),
+            LogRotation::Size => paths.push(rotated_path(&self.path, "1")),
+            LogRotation::Never => {}
+        }
+        paths
+    }
+}
+
+/// When the log file is rotated
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogRotation {
+    /// Once a day, rotated files are named after the day, e.g. gitwatch.log.2025-01-31
+    #[default]
+    Daily,
+    /// Once the file exceeds the max size, rotated files are numbered, e.g. gitwatch.log.1
+    Size,
+    /// The file grows indefinitely
+    Never,
+}
+
+/// Log file that is rotated according to its settings.
+/// Rotated files older than the retention are removed.
+pub struct RotatingFile {
+    // day of the records in the file, as "YYYY-MM-DD" in local time
+    date: String,
+    file: File,
+    settings: LogFile,
+    size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(settings: &LogFile) -> Result<Self> {
+        if let Some(parent) = settings.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = open_file(&settings.path)
+            .with_context(|| format!("Failed to open log file {}", settings.path.display()))?;
+        let metadata = file.metadata()?;
+        Ok(Self {
+            date: local_date(metadata.modified().unwrap_or_else(|_| SystemTime::now())),
+            file,
+            settings: settings.clone(),
+            size: metadata.len(),
+        })
+    }
+
+    pub fn settings(&self) -> &LogFile {
+        &self.settings
+    }
+
+    /// Appends a log record, rotating the file first if needed
+    pub fn write_record(&mut self, record: &str, now: SystemTime) -> io::Result<()> {
+        let date = local_date(now);
+        let needs_rotation = self.size > 0
+            && match self.settings.rotation {
+                LogRotation::Daily => date != self.date,
+                LogRotation::Size => {
+                    self.size + record.len() as u64 > self.settings.max_size_mb * 1024 * 1024
+                }
+                LogRotation::Never => false,
+            };
+        if needs_rotation {
+            self.rotate()?;
+        }
+        self.date = date;
+
+        self.file.write_all(record.as_bytes())?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = &self.settings.path;
+        let retention = self.settings.retention;
+        if retention == 0 {
+            fs::remove_file(path)?;
+        } else {
+            match self.settings.rotation {
+                LogRotation::Daily => {
+                    fs::rename(path, rotated_path(path, &self.date))?;
+                    let mut rotated_paths = find_rotated_paths(path)?;
+                    // the dates sort chronologically
+                    rotated_paths.sort();
+                    let excess = rotated_paths.len().saturating_sub(retention);
+                    for rotated_path in &rotated_paths[..excess] {
+                        fs::remove_file(rotated_path)?;
+                    }
+                }
+                LogRotation::Size | LogRotation::Never => {
+                    for number in (1..retention).rev() {
+                        let rotated = rotated_path(path, &number.to_string());
+                        if rotated.exists() {
+                            fs::rename(rotated, rotated_path(path, &(number + 1).to_string()))?;
+                        }
+                    }
+                    fs::rename(path, rotated_path(path, "1"))?;
+                }
+            }
+        }
+
+        // the old file is closed when dropped
+        drop(mem::replace(&mut self.file, open_file(path)?));
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{suffix}"));
+    PathBuf::from(rotated)
+}
+
+// Returns the files rotated by daily rotation, e.g. gitwatch.log.2025-01-31
+fn find_rotated_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(file_name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{file_name}.");
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+
+    let mut rotated_paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.strip_prefix(&prefix).is_some_and(is_date) {
+            rotated_paths.push(entry.path());
+        }
+    }
+    Ok(rotated_paths)
+}
+
+// e.g. 2025-01-31
+fn is_date(suffix: &str) -> bool {
+    suffix.len() == 10
+        && suffix.chars().enumerate().all(|(i, c)| {
+            if i == 4 || i == 7 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+}
+
+fn is_number(suffix: &str) -> bool {
+    !suffix.is_empty() && suffix.chars().all(|c| c.is_ascii_digit())
+}
+
+// Returns the date of `time` in the local timezone, as "YYYY-MM-DD"
+fn local_date(time: SystemTime) -> String {
+    let seconds = unix_timestamp(time) as libc::time_t;
+    // SAFETY: tm is a plain C struct, for which all zeroes are valid
+    let mut tm: libc::tm = unsafe { mem::zeroed() };
+    // SAFETY: both pointers are valid for the duration of the call
+    if unsafe { libc::localtime_r(&seconds, &mut tm) }.is_null() {
+        // fall back to UTC
+        return humantime::format_rfc3339(time).to_string()[..10].to_string();
+    }
+    format!(
+        "{:04}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use testresult::TestResult;
+
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn test_settings(dir: &Path, rotation: LogRotation) -> LogFile {
+        LogFile {
+            level: LogLevel::Debug,
+            max_size_mb: 1,
+            path: dir.join("gitwatch.log"),
+            retention: 2,
+            rotation,
+        }
+    }
+
+    #[test]
+    fn test_daily_rotation() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let settings = test_settings(temp_dir.path(), LogRotation::Daily);
+        let mut file = RotatingFile::open(&settings)?;
+
+        let start = SystemTime::now();
+        for day in 0..4 {
+            let now = start + DAY * day;
+            file.write_record(&format!("day {day}\n"), now)?;
+            file.write_record(&format!("day {day} again\n"), now)?;
+        }
+
+        assert_eq!(
+            fs::read_to_string(&settings.path)?,
+            "day 3\nday 3 again\n"
+        );
+        let mut rotated_paths = find_rotated_paths(&settings.path)?;
+        rotated_paths.sort();
+        assert_eq!(
+            rotated_paths,
+            vec![
+                rotated_path(&settings.path, &local_date(start + DAY)),
+                rotated_path(&settings.path, &local_date(start + DAY * 2)),
+            ]
+        );
+        assert_eq!(fs::read_to_string(&rotated_paths[1])?, "day 2\nday 2 again\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_rotation() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let settings = test_settings(temp_dir.path(), LogRotation::Size);
+        let mut file = RotatingFile::open(&settings)?;
+
+        // each record fills more than half of the file
+        let now = SystemTime::now();
+        for i in 0..4 {
+            let record = format!("{i}{}\n", "x".repeat(600 * 1024));
+            file.write_record(&record, now)?;
+        }
+
+        let first_char = |path: &Path| -> Result<char> {
+            Ok(fs::read_to_string(path)?.chars().next().unwrap_or_default())
+        };
+        assert_eq!(first_char(&settings.path)?, '3');
+        assert_eq!(first_char(&rotated_path(&settings.path, "1"))?, '2');
+        assert_eq!(first_char(&rotated_path(&settings.path, "2"))?, '1');
+        assert!(!rotated_path(&settings.path, "3").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reopen() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let settings = test_settings(temp_dir.path(), LogRotation::Never);
+        RotatingFile::open(&settings)?.write_record("first\n", SystemTime::now())?;
+        RotatingFile::open(&settings)?.write_record("second\n", SystemTime::now())?;
+        assert_eq!(fs::read_to_string(&settings.path)?, "first\nsecond\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_log_path() {
+        let settings = test_settings(Path::new("/repo"), LogRotation::Daily);
+        let path = |name: &str| Path::new("/repo").join(name);
+        assert!(settings.is_log_path(&settings.path));
+        assert!(settings.is_log_path(&rotated_path(&settings.path, "2025-01-31")));
+        assert!(settings.is_log_path(&rotated_path(&settings.path, "12")));
+
+        assert!(!settings.is_log_path(&rotated_path(&settings.path, "bak")));
+        assert!(!settings.is_log_path(&path("other.log.1")));
+        assert!(!settings.is_log_path(&path("subdir").join(settings.path.file_name().unwrap())));
+
+        for rotation in [LogRotation::Daily, LogRotation::Size, LogRotation::Never] {
+            let settings = LogFile {
+                rotation,
+                ..settings.clone()
+            };
+            let sample_paths = settings.sample_paths();
+            assert_eq!(sample_paths.len(), if rotation == LogRotation::Never { 1 } else { 2 });
+            assert!(sample_paths.iter().all(|path| settings.is_log_path(path)));
+        }
+    }
+}
@@ -1,15 +1,149 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock, Mutex,
+    },
+    time::SystemTime,
+};
+
 use anyhow::Result;
 use fern::{
     colors::{Color, ColoredLevelConfig},
-    Dispatch,
+    Dispatch, Output,
+};
+use log::{
+    kv::{self, Key, Value, VisitSource},
+    Level, LevelFilter, Record,
 };
-use log::Level;
+use regex::Regex;
+
+use crate::{
+    cli::{LogFormat, LogLevel},
+    log_file::{LogFile, RotatingFile},
+};
+
+static ANSI_ESCAPE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("\x1B\\[[0-9;]*m").unwrap());
+
+// the log file is only known once the config file is loaded, see `set_log_file`
+static LOG_FILE: Mutex<Option<RotatingFile>> = Mutex::new(None);
+static LOG_FILE_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+static CONSOLE_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+
+/// Configures the global logger
+pub struct Logger {
+    format: LogFormat,
+    level: LogLevel,
+    repository: Option<String>,
+}
+
+impl Logger {
+    pub fn new(level: LogLevel) -> Self {
+        Self {
+            format: LogFormat::Text,
+            level,
+            repository: None,
+        }
+    }
+
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
 
-use crate::cli::LogLevel;
+    /// Adds the repository to every structured log record
+    pub fn with_repository(mut self, repository: &Path) -> Self {
+        self.repository = Some(repository.display().to_string());
+        self
+    }
+
+    pub fn apply(self) -> Result<()> {
+        // each sink filters by its own level
+        let base_config = Dispatch::new()
+            .level(LevelFilter::Trace)
+            .level_for("ignore::gitignore", log::LevelFilter::Warn)
+            .level_for("globset", log::LevelFilter::Warn);
+
+        let base_config = match self.format {
+            LogFormat::Text => {
+                let stdout_config = text_format(use_colors(io::stdout().is_terminal()))
+                    .level(self.level.into())
+                    .filter(|metadata| metadata.level() == Level::Info)
+                    .chain(io::stdout());
+                let stderr_config = text_format(use_colors(io::stderr().is_terminal()))
+                    .level(self.level.into())
+                    .filter(|metadata| metadata.level() != Level::Info)
+                    .chain(io::stderr());
+                base_config.chain(stdout_config).chain(stderr_config)
+            }
+            // a single stream keeps the records in order for log shippers
+            LogFormat::Json | LogFormat::Logfmt => base_config.chain(
+                structured_format(self.format, self.repository.clone())
+                    .level(self.level.into())
+                    .chain(io::stderr()),
+            ),
+        };
+        let file_format = match self.format {
+            LogFormat::Text => file_text_format(),
+            LogFormat::Json | LogFormat::Logfmt => structured_format(self.format, self.repository),
+        };
+        let file_config = file_format
+            .filter(|metadata| metadata.level() as usize <= LOG_FILE_LEVEL.load(Ordering::Relaxed))
+            .chain(Output::call(write_log_file));
+        base_config.chain(file_config).apply()?;
+
+        CONSOLE_LEVEL.store(LevelFilter::from(self.level) as usize, Ordering::Relaxed);
+        update_max_level();
+        Ok(())
+    }
+}
 
 pub fn setup_logger(level: LogLevel) -> Result<()> {
-    let filter_level = level.into();
+    Logger::new(level).apply()
+}
 
+/// Writes the logs to `log_file` in addition to stdout & stderr, or stops writing them to a file.
+/// Can be called again when the config is reloaded, the file is only reopened if its settings changed.
+pub fn set_log_file(log_file: Option<&LogFile>) -> Result<()> {
+    let mut current_file = LOG_FILE.lock().unwrap();
+    if current_file.as_ref().map(|file| file.settings()) == log_file {
+        return Ok(());
+    }
+    *current_file = log_file.map(RotatingFile::open).transpose()?;
+    let level = log_file.map_or(LevelFilter::Off, |log_file| log_file.level.into());
+    LOG_FILE_LEVEL.store(level as usize, Ordering::Relaxed);
+    update_max_level();
+    Ok(())
+}
+
+// the dispatch accepts every level so the file can be more verbose than the console,
+// the global max level skips formatting the records that no sink would write
+fn update_max_level() {
+    let level = CONSOLE_LEVEL
+        .load(Ordering::Relaxed)
+        .max(LOG_FILE_LEVEL.load(Ordering::Relaxed));
+    log::set_max_level(LevelFilter::iter().nth(level).unwrap_or(LevelFilter::Trace));
+}
+
+fn write_log_file(record: &Record) {
+    let mut log_file = LOG_FILE.lock().unwrap();
+    if let Some(log_file) = log_file.as_mut() {
+        // logging the error would end up here again
+        if let Err(e) = log_file.write_record(&format!("{}\n", record.args()), SystemTime::now()) {
+            eprintln!("Failed to write log file: {e}");
+        }
+    }
+}
+
+// see https://no-color.org
+fn use_colors(is_terminal: bool) -> bool {
+    is_terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+}
+
+fn text_format(use_colors: bool) -> Dispatch {
     let colors_line = ColoredLevelConfig::new()
         .error(Color::Red)
         .warn(Color::Yellow)
@@ -19,37 +153,183 @@ pub fn setup_logger(level: LogLevel) -> Result<()> {
         // depending on the terminals color scheme, this is the same as the background color
         .trace(Color::BrightBlack);
 
-    let base_config = Dispatch::new()
-        .format(move |out, message, record| {
-            let level = &record.level();
+    Dispatch::new().format(move |out, message, record| {
+        let level = &record.level();
+        let level_prefix = if level == &Level::Info {
+            "".to_string()
+        } else {
+            format!("[{level}] ", level = level.to_string().to_lowercase())
+        };
+        if use_colors {
             let color = colors_line.get_color(level).to_fg_str();
             let color_prefix = format!("\x1B[{color}m");
             const COLOR_SUFFIX: &str = "\x1B[0m";
-            let level_prefix = if level == &Level::Info {
-                "".to_string()
-            } else {
-                format!("[{level}] ", level = level.to_string().to_lowercase())
-            };
             out.finish(format_args!(
                 "{color_prefix}{level_prefix}{message}{COLOR_SUFFIX}"
             ));
+        } else {
+            // some messages are colorized themselves
+            let message = message.to_string();
+            out.finish(format_args!(
+                "{level_prefix}{}",
+                ANSI_ESCAPE_REGEX.replace_all(&message, "")
+            ));
+        }
+    })
+}
+
+// unlike the console, the file has no other timestamps & is read without colors
+fn file_text_format() -> Dispatch {
+    Dispatch::new().format(|out, message, record| {
+        let message = message.to_string();
+        out.finish(format_args!(
+            "{} {:<5} {}",
+            humantime::format_rfc3339_millis(SystemTime::now()),
+            record.level(),
+            ANSI_ESCAPE_REGEX.replace_all(&message, "")
+        ))
+    })
+}
+
+fn structured_format(format: LogFormat, repository: Option<String>) -> Dispatch {
+    Dispatch::new().format(move |out, message, record| {
+        let fields = collect_fields(
+            SystemTime::now(),
+            repository.as_deref(),
+            &message.to_string(),
+            record,
+        );
+        match format {
+            LogFormat::Json => {
+                let object = serde_json::Value::Object(fields.into_iter().collect());
+                out.finish(format_args!("{object}"));
+            }
+            _ => out.finish(format_args!("{}", format_logfmt(&fields))),
+        }
+    })
+}
+
+// Returns the fields of a structured log record, including the key-values of the record,
+// e.g. `info!(event = "commit", files = 2; "...")`
+fn collect_fields(
+    timestamp: SystemTime,
+    repository: Option<&str>,
+    message: &str,
+    record: &Record,
+) -> Vec<(String, serde_json::Value)> {
+    let mut fields = vec![
+        (
+            "timestamp".to_string(),
+            humantime::format_rfc3339_millis(timestamp)
+                .to_string()
+                .into(),
+        ),
+        (
+            "level".to_string(),
+            record.level().to_string().to_lowercase().into(),
+        ),
+    ];
+    if let Some(repository) = repository {
+        fields.push(("repo".to_string(), repository.into()));
+    }
+    fields.push((
+        "message".to_string(),
+        ANSI_ESCAPE_REGEX.replace_all(message, "").into(),
+    ));
+    let _ = record.key_values().visit(&mut FieldCollector(&mut fields));
+    fields
+}
+
+struct FieldCollector<'a>(&'a mut Vec<(String, serde_json::Value)>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_bool() {
+            value.into()
+        } else if let Some(value) = value.to_u64() {
+            value.into()
+        } else if let Some(value) = value.to_i64() {
+            value.into()
+        } else if let Some(value) = value.to_f64() {
+            value.into()
+        } else {
+            value.to_string().into()
+        };
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+// formats the fields as `key=value` pairs, see https://brandur.org/logfmt
+fn format_logfmt(fields: &[(String, serde_json::Value)]) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::String(value) => {
+                    if value.is_empty()
+                        || value.contains(|c: char| c.is_whitespace() || c == '=' || c == '"')
+                    {
+                        // escapes quotes, backslashes & newlines
+                        serde_json::Value::String(value.clone()).to_string()
+                    } else {
+                        value.clone()
+                    }
+                }
+                value => value.to_string(),
+            };
+            format!("{key}={value}")
         })
-        .level(filter_level)
-        .level_for("ignore::gitignore", log::LevelFilter::Warn)
-        .level_for("globset", log::LevelFilter::Warn);
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-    let stdout_config = Dispatch::new()
-        .filter(|metadata| metadata.level() == log::Level::Info)
-        .chain(std::io::stdout());
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
 
-    let stderr_config = Dispatch::new()
-        .filter(|metadata| metadata.level() != log::Level::Info)
-        .chain(std::io::stderr());
+    use super::*;
 
-    base_config
-        .chain(stdout_config)
-        .chain(stderr_config)
-        .apply()?;
+    fn test_fields() -> Vec<(String, serde_json::Value)> {
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(60);
+        let kvs: &[(&str, Value)] = &[
+            ("event", Value::from("commit")),
+            ("files", Value::from(2u64)),
+        ];
+        let record = Record::builder()
+            .level(Level::Info)
+            .key_values(&kvs)
+            .args(format_args!(""))
+            .build();
+        collect_fields(
+            timestamp,
+            Some("/repo"),
+            "\x1B[4mCreated\x1B[0m commit",
+            &record,
+        )
+    }
 
-    Ok(())
+    #[test]
+    fn test_json_fields() {
+        let object = serde_json::Value::Object(test_fields().into_iter().collect());
+        assert_eq!(
+            object,
+            serde_json::json!({
+                "timestamp": "1970-01-01T00:01:00.000Z",
+                "level": "info",
+                "repo": "/repo",
+                "message": "Created commit",
+                "event": "commit",
+                "files": 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_logfmt() {
+        assert_eq!(
+            format_logfmt(&test_fields()),
+            r#"timestamp=1970-01-01T00:01:00.000Z level=info repo=/repo message="Created commit" event=commit files=2"#
+        );
+    }
 }
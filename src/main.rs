@@ -1,19 +1,31 @@
-use std::process;
+use std::{
+    io::{self, Write},
+    path::Path,
+    process,
+    sync::mpsc,
+};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use clap::{CommandFactory, Parser};
 use clap_complete::generate;
 use gitwatch_rs::{
     app::App,
     app_config::AppConfig,
-    cli::{Cli, Commands},
-    logger::setup_logger,
+    cli::{Cli, Commands, LogLevel},
+    control::{send_request, ControlRequest, ControlSocket},
+    fsmonitor,
+    logger::{set_log_file, setup_logger, Logger},
+    metrics::MetricsServer,
+    service,
+    signals::handle_signals,
+    status::StatusReport,
+    util::normalize_path,
 };
 use log::error;
 
 fn main() {
     if let Err(e) = run() {
-        error!("{e:?}");
+        error!(event = "error"; "{e:?}");
         process::exit(1);
     }
 }
@@ -23,10 +35,83 @@ fn run() -> Result<()> {
 
     match cli.command {
         Commands::Watch(cli_opts) => {
-            setup_logger(cli_opts.log_level)?;
-            let config = AppConfig::new(cli_opts)?;
-            let app = App::new(config)?;
-            app.run(None)
+            let repository = normalize_path(&cli_opts.repository)
+                .unwrap_or_else(|_| cli_opts.repository.clone());
+            Logger::new(cli_opts.log_level)
+                .with_format(cli_opts.log_format)
+                .with_repository(&repository)
+                .apply()?;
+            let config = AppConfig::new((*cli_opts).clone())?;
+            set_log_file(config.log_file.as_ref())?;
+            let repository = config.repository.clone();
+            let metrics_addr = config.metrics_addr;
+            let mut app = App::new(config)?.with_config_loader(move || {
+                let config = AppConfig::reload((*cli_opts).clone())?;
+                set_log_file(config.log_file.as_ref())?;
+                Ok(config)
+            });
+
+            let (control_tx, control_rx) = mpsc::channel();
+            handle_signals(control_tx.clone())?;
+            let _control_socket = ControlSocket::bind(&repository, control_tx, app.state())?;
+            // changing the address requires a restart
+            if let Some(metrics_addr) = metrics_addr {
+                MetricsServer::bind(metrics_addr, app.state())?;
+            }
+            app.run(Some(control_rx))
+        }
+        Commands::Ctl {
+            repository,
+            request,
+        } => {
+            setup_logger(LogLevel::Info)?;
+            let repository = normalize_path(&repository).context(format!(
+                "Invalid repository path '{}'",
+                repository.display()
+            ))?;
+            let response = send_request(&repository, &request)?;
+            if let Some(status) = response.status {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            }
+            Ok(())
+        }
+        Commands::Status { repository, json } => {
+            setup_logger(LogLevel::Info)?;
+            let repository = normalize_path(&repository).context(format!(
+                "Invalid repository path '{}'",
+                repository.display()
+            ))?;
+            let report = StatusReport::new(&repository)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{report}");
+            }
+            Ok(())
+        }
+        Commands::Fsmonitor { version, token } => {
+            setup_logger(LogLevel::Info)?;
+            if version != fsmonitor::HOOK_VERSION {
+                bail!("Unsupported fsmonitor hook version {version}");
+            }
+            // git runs the hook in the root of the working tree
+            let repository = normalize_path(Path::new("."))?;
+            let request = ControlRequest::FsMonitor {
+                token: token.unwrap_or_default(),
+            };
+            // git scans the working tree itself if the hook fails, e.g. while gitwatch isn't running
+            let Ok(response) = send_request(&repository, &request) else {
+                process::exit(1);
+            };
+            let response = response
+                .fsmonitor
+                .context("Invalid response from control socket")?;
+            io::stdout().write_all(&response.to_hook_output())?;
+            Ok(())
+        }
+        Commands::Service { command } => {
+            setup_logger(LogLevel::Info)?;
+            service::run(command)
         }
         Commands::Completion { shell } => {
             let mut cmd = Cli::command();
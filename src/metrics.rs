@@ -0,0 +1,271 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use log::{debug, info, warn};
+
+use crate::state::AppState;
+
+/// Counters & gauges of a gitwatch instance, exposed in the Prometheus text format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    commits_total: AtomicU64,
+    config_reloads_total: AtomicU64,
+    ignored_events_total: AtomicU64,
+    last_commit_timestamp: AtomicU64,
+    pending_changes: AtomicU64,
+    push_failures_total: AtomicU64,
+    pushes_total: AtomicU64,
+    // sum & count of the commit message script durations
+    script_duration: Mutex<(Duration, u64)>,
+    watcher_errors_total: AtomicU64,
+}
+
+impl Metrics {
+    pub fn record_commit(&self, timestamp: u64) {
+        self.commits_total.fetch_add(1, Ordering::Relaxed);
+        self.last_commit_timestamp
+            .store(timestamp, Ordering::Relaxed);
+    }
+
+    pub fn record_config_reload(&self) {
+        self.config_reloads_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_ignored_events(&self, count: usize) {
+        self.ignored_events_total
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_push(&self) {
+        self.pushes_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_push_failure(&self) {
+        self.push_failures_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_script_duration(&self, duration: Duration) {
+        let mut script_duration = self.script_duration.lock().unwrap();
+        script_duration.0 += duration;
+        script_duration.1 += 1;
+    }
+
+    pub fn record_watcher_error(&self) {
+        self.watcher_errors_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of changed paths detected, but not committed yet
+    pub fn set_pending_changes(&self, count: usize) {
+        self.pending_changes.store(count as u64, Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format
+    pub fn render(&self, repository: &Path, paused: bool) -> String {
+        let label = format!("{{repo=\"{}\"}}", escape_label_value(repository));
+        let counter = |name, help, value: &AtomicU64| {
+            format!(
+                "# HELP gitwatch_{name} {help}\n# TYPE gitwatch_{name} counter\ngitwatch_{name}{label} {}\n",
+                value.load(Ordering::Relaxed)
+            )
+        };
+        let gauge = |name, help, value: u64| {
+            format!("# HELP gitwatch_{name} {help}\n# TYPE gitwatch_{name} gauge\ngitwatch_{name}{label} {value}\n")
+        };
+
+        let mut output = String::new();
+        output += &counter(
+            "commits_total",
+            "Number of created commits",
+            &self.commits_total,
+        );
+        output += &counter(
+            "config_reloads_total",
+            "Number of successful config reloads",
+            &self.config_reloads_total,
+        );
+        output += &counter(
+            "ignored_events_total",
+            "Number of filesystem events for ignored paths",
+            &self.ignored_events_total,
+        );
+        output += &gauge(
+            "last_commit_timestamp_seconds",
+            "Unix time of the last commit, 0 if nothing was committed yet",
+            self.last_commit_timestamp.load(Ordering::Relaxed),
+        );
+        output += &gauge(
+            "paused",
+            "Whether autocommits are paused manually",
+            paused.into(),
+        );
+        output += &gauge(
+            "pending_changes",
+            "Number of changed paths that are not committed yet",
+            self.pending_changes.load(Ordering::Relaxed),
+        );
+        output += &counter(
+            "push_failures_total",
+            "Number of failed pushes",
+            &self.push_failures_total,
+        );
+        output += &counter("pushes_total", "Number of pushes", &self.pushes_total);
+
+        let (script_duration, script_count) = *self.script_duration.lock().unwrap();
+        let _ = write!(
+            output,
+            "# HELP gitwatch_script_duration_seconds Duration of the commit message script\n\
+            # TYPE gitwatch_script_duration_seconds summary\n\
+            gitwatch_script_duration_seconds_sum{label} {}\n\
+            gitwatch_script_duration_seconds_count{label} {script_count}\n",
+            script_duration.as_secs_f64()
+        );
+
+        output += &counter(
+            "watcher_errors_total",
+            "Number of errors reported by the file watcher",
+            &self.watcher_errors_total,
+        );
+        output
+    }
+}
+
+// see https://prometheus.io/docs/instrumenting/exposition_formats/#text-format-details
+fn escape_label_value(path: &Path) -> String {
+    path.display()
+        .to_string()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serves the metrics of a running instance via HTTP at `/metrics`
+pub struct MetricsServer {
+    pub address: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Binds `address` and handles requests in a background thread
+    pub fn bind(address: SocketAddr, state: Arc<AppState>) -> Result<Self> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Failed to bind metrics address {address}"))?;
+        let address = listener.local_addr()?;
+        info!("Serving metrics at http://{address}/metrics");
+
+        thread::Builder::new()
+            .name("gitwatch metrics".to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    match stream {
+                        Ok(stream) => {
+                            if let Err(e) = handle_connection(stream, &state) {
+                                debug!("Metrics request failed: {e}");
+                            }
+                        }
+                        Err(e) => warn!("Failed to accept metrics connection: {e}"),
+                    }
+                }
+            })?;
+
+        Ok(Self { address })
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, state: &AppState) -> Result<()> {
+    // a stalled client must not block other scrapes
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers are not needed
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", state.render_metrics()),
+        _ => (
+            "404 Not Found",
+            "Not found, metrics are served at /metrics\n".to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, path::PathBuf};
+
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record_commit(1700000000);
+        metrics.record_commit(1700000060);
+        metrics.record_ignored_events(3);
+        metrics.record_script_duration(Duration::from_millis(250));
+        metrics.record_script_duration(Duration::from_millis(500));
+        metrics.set_pending_changes(2);
+
+        let output = metrics.render(Path::new("/home/user/\"notes\""), false);
+        let label = r#"{repo="/home/user/\"notes\""}"#;
+        for expected_line in [
+            "# TYPE gitwatch_commits_total counter".to_string(),
+            format!("gitwatch_commits_total{label} 2"),
+            format!("gitwatch_ignored_events_total{label} 3"),
+            format!("gitwatch_last_commit_timestamp_seconds{label} 1700000060"),
+            format!("gitwatch_paused{label} 0"),
+            format!("gitwatch_pending_changes{label} 2"),
+            format!("gitwatch_push_failures_total{label} 0"),
+            format!("gitwatch_script_duration_seconds_sum{label} 0.75"),
+            format!("gitwatch_script_duration_seconds_count{label} 2"),
+            format!("gitwatch_watcher_errors_total{label} 0"),
+        ] {
+            assert!(
+                output.lines().any(|line| line == expected_line),
+                "Missing '{expected_line}' in:\n{output}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_server() -> TestResult {
+        let state = Arc::new(AppState::new(PathBuf::from("/notes")));
+        state.record_commit();
+        let server = MetricsServer::bind("127.0.0.1:0".parse()?, state)?;
+
+        let get = |path: &str| -> Result<String> {
+            let mut stream = TcpStream::connect(server.address)?;
+            write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        };
+
+        let response = get("/metrics")?;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\ngitwatch_commits_total{repo=\"/notes\"} 1\n"));
+        assert!(get("/")?.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        Ok(())
+    }
+}
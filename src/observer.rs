@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+/// Receives the events of a running `App`, e.g. to show them in an editor or GUI.
+///
+/// The methods are called on the watch thread, so they should return quickly
+/// and hand longer work off to another thread. All methods do nothing by default.
+pub trait GitwatchObserver: Send + Sync {
+    /// Changes were detected and are about to be committed.
+    /// Not called for interval commits, which are not triggered by specific paths.
+    fn on_changes_detected(&self, _paths: &[PathBuf]) {}
+
+    /// Changes were committed
+    fn on_commit(&self, _commit_id: &str, _files: &[String], _message: &str) {}
+
+    /// Commits were pushed to `remote`
+    fn on_push(&self, _remote: &str) {}
+
+    /// Pushing to `remote` failed, the commit was created nonetheless
+    fn on_push_failure(&self, _remote: &str, _error: &str) {}
+
+    /// Committing failed after all retries & the app stops,
+    /// or HEAD is detached with the `error` policy & autocommits are paused
+    fn on_error(&self, _error: &str) {}
+
+    /// Autocommits were paused, manually (e.g. via `AppState::set_paused` or the control socket)
+    /// or e.g. by a mass deletion or a rebase in progress
+    fn on_paused(&self, _reason: &str) {}
+}
@@ -1,47 +1,134 @@
 use std::{
+    collections::{BTreeSet, HashSet},
     fmt::{self, Display, Formatter},
+    fs,
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 
 use anyhow::{bail, Context, Result};
 use auth_git2::GitAuthenticator;
-use git2::{Oid, Repository, Status, StatusOptions};
+use git2::{IndexAddOption, Oid, Repository, RepositoryState, Status, StatusOptions, StatusShow};
 use indoc::formatdoc;
-use log::{debug, info, trace, warn};
+use log::{debug, info, log, trace, warn, Level};
 use paris::formatter::colorize_string;
 use regex::Regex;
 
-use crate::commit_message::generate_commit_message;
+use crate::{
+    app_config::AppConfig,
+    branch::{BranchPolicy, BranchSwitchPolicy, DetachedHeadPolicy},
+    commit_message::{
+        Change, ChangeSet, ChangeStatus, CommitMessageProvider, ScriptMessage, StaticMessage,
+    },
+    events::{Event, EventEmitter},
+    hooks::GitHooks,
+    stage::StagePolicy,
+    state::AppState,
+};
+
+// Holder of an index lock without a git operation in progress, e.g. an editor's `git status`
+const INDEX_LOCK_HOLDER: &str = "another git process";
+
+// Number of changes staged in the index
+#[derive(Debug, Default)]
+struct StagedChanges {
+    added: usize,
+    deleted: usize,
+    total: usize,
+    tracked: usize,
+}
+
+// Which part of the working tree is staged
+enum StageScope {
+    // the whole working tree, catching changes without (or with missed) filesystem events
+    All,
+    // repository-relative paths of the detected changes, including directories
+    Paths(Vec<String>),
+}
+
+impl StageScope {
+    // libgit2 matches `add_all`'s pathspecs as globs despite `DISABLE_PATHSPEC_MATCH`,
+    // so a changed path like `file[1].txt` would also stage `file1.txt`
+    fn contains(&self, path: &Path) -> bool {
+        match self {
+            Self::All => true,
+            // a directory contains all paths inside it
+            Self::Paths(paths) => paths
+                .iter()
+                .any(|changed_path| path.starts_with(changed_path)),
+        }
+    }
+}
+
+impl Display for StageScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "the whole working tree"),
+            Self::Paths(paths) => write!(f, "{} changed paths", paths.len()),
+        }
+    }
+}
 
 pub struct GitwatchRepo {
-    commit_message: Option<String>,
+    branch_policy: BranchPolicy,
+    // None if a custom provider is set via `with_commit_message_provider`
+    commit_message_provider: Option<Arc<dyn CommitMessageProvider>>,
     commit_message_script: Option<PathBuf>,
+    // branch checked out during the last change, None if HEAD is detached
+    current_branch: Mutex<Option<String>>,
     dry_run: bool,
+    events: Arc<EventEmitter>,
     ignore_regex: Option<Regex>,
+    // branch checked out when gitwatch was started
+    initial_branch: Option<String>,
+    // when the whole working tree was staged the last time
+    last_full_scan: Mutex<Instant>,
     remote: Option<String>,
     git_repo: Repository,
+    // reason why autocommits are currently paused, if any
+    pause_reason: Mutex<Option<String>>,
+    reconcile_interval: Duration,
     repo_path: PathBuf,
+    run_hooks: bool,
+    // the configured script generates the commit messages, not a custom provider
+    script_message_active: bool,
+    stage_policy: StagePolicy,
+    state: Arc<AppState>,
 }
 
 impl GitwatchRepo {
-    pub fn new(
-        repo_path: &Path,
-        commit_message: Option<String>,
-        commit_message_script: Option<PathBuf>,
-        ignore_regex: Option<Regex>,
-        dry_run: bool,
-        remote: Option<String>,
-    ) -> Result<Self> {
+    pub fn new(config: &AppConfig) -> Result<Self> {
+        let repo_path = &config.repository;
         debug!("Opening git repository {}", repo_path.display());
         let repo = Repository::open(repo_path)?;
+        let initial_branch = get_current_branch(&repo)?;
+        let commit_message_provider: Option<Arc<dyn CommitMessageProvider>> =
+            match (&config.commit_message, &config.commit_message_script) {
+                (Some(message), _) => Some(Arc::new(StaticMessage(message.clone()))),
+                (None, Some(script_path)) => Some(Arc::new(ScriptMessage(script_path.clone()))),
+                (None, None) => None,
+            };
         let gitwatch_repo = Self {
+            branch_policy: config.branch_policy.clone(),
+            commit_message_provider,
+            commit_message_script: config.commit_message_script.clone(),
+            current_branch: Mutex::new(initial_branch.clone()),
+            dry_run: config.dry_run,
+            events: Arc::new(EventEmitter::new(config)),
+            ignore_regex: config.ignore_regex.clone(),
+            initial_branch,
+            last_full_scan: Mutex::new(Instant::now()),
+            remote: config.remote.clone(),
             git_repo: repo,
+            pause_reason: Mutex::new(None),
+            reconcile_interval: Duration::from_secs(config.reconcile_interval_seconds),
             repo_path: repo_path.to_path_buf(),
-            commit_message,
-            commit_message_script,
-            dry_run,
-            ignore_regex,
-            remote,
+            run_hooks: config.run_hooks,
+            script_message_active: config.commit_message.is_none()
+                && config.commit_message_script.is_some(),
+            stage_policy: config.stage_policy.clone(),
+            state: Arc::new(AppState::new(repo_path.to_path_buf())),
         };
         gitwatch_repo.validate_commit_message_script()?;
         gitwatch_repo.validate_remote()?;
@@ -53,50 +140,375 @@ impl GitwatchRepo {
         Ok(gitwatch_repo)
     }
 
-    pub fn process_changes(&self) -> Result<()> {
-        let has_staged_changes = self.stage_changes().context("Failed to stage changes")?;
-        if !has_staged_changes {
+    /// Shares the one-off commit message with other components, e.g. the control socket
+    pub fn with_state(mut self, state: Arc<AppState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Generates commit messages with `provider` instead of the configured message or script
+    pub fn with_commit_message_provider(
+        mut self,
+        provider: Arc<dyn CommitMessageProvider>,
+    ) -> Self {
+        self.commit_message_provider = Some(provider);
+        self.script_message_active = false;
+        self
+    }
+
+    /// Shares the event hooks with other components, e.g. the file watcher
+    pub fn with_events(mut self, events: Arc<EventEmitter>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Path of the `.git` directory, or of the worktree's git directory
+    pub fn git_dir(&self) -> &Path {
+        self.git_repo.path()
+    }
+
+    /// Stages & commits the changes of `paths`, or of the whole working tree if `paths` is empty
+    /// or the reconcile interval has elapsed since the last full scan.
+    /// Returns the id of the created commit, if any.
+    pub fn process_changes(&self, paths: &[PathBuf]) -> Result<Option<Oid>> {
+        if let Some((reason, level)) = self.get_pause_reason()? {
+            // failing would end the watch loop, so errors are reported once when pausing
+            if self.pause(reason.clone(), level) && level == Level::Error {
+                self.events.emit(Event::Error { error: reason });
+            }
+            return Ok(None);
+        }
+
+        let scope = self.get_stage_scope(paths);
+        let staged_changes = self
+            .stage_changes(&scope)
+            .context("Failed to stage changes")?;
+        if let StageScope::All = scope {
+            *self.last_full_scan.lock().unwrap() = Instant::now();
+        }
+        if let Some(reason) = self.check_mass_deletion(&staged_changes) {
+            self.state.set_mass_deletion_paused(true);
+            self.pause(reason, Level::Error);
+            return Ok(None);
+        }
+        self.state.set_mass_deletion_paused(false);
+        self.resume();
+
+        if staged_changes.total == 0 {
             debug!("Working tree clean");
-            return Ok(());
+            return Ok(None);
         }
 
         if self.dry_run {
             self.log_pending_commit()?;
-        } else {
-            self.commit_and_push()?;
+            return Ok(None);
         }
-        Ok(())
+        self.commit_and_push(&staged_changes)
     }
 
-    // Returns true if the index contains any staged changes
-    fn stage_changes(&self) -> Result<bool> {
-        let mut index = self.git_repo.index()?;
-        index.add_all(
-            ["*"].iter(),
-            git2::IndexAddOption::DEFAULT,
-            Some(&mut |path, _matched_spec| {
-                if self.is_path_ignored(path) {
-                    1
-                } else {
-                    0
+    /// Stages the changes of `paths` like `process_changes`, without committing them.
+    /// Returns the number of staged changes, used by the benchmarks in `benches/`.
+    #[doc(hidden)]
+    pub fn stage_paths(&self, paths: &[PathBuf]) -> Result<usize> {
+        let scope = self.get_stage_scope(paths);
+        Ok(self.stage_changes(&scope)?.total)
+    }
+
+    /// Returns the number of changes staged in the index, used by the benchmarks in `benches/`
+    #[doc(hidden)]
+    pub fn staged_change_count(&self) -> Result<usize> {
+        Ok(self.get_staged_changes()?.total)
+    }
+
+    // Returns the reason why autocommits are currently not possible & its log level, if any
+    fn get_pause_reason(&self) -> Result<Option<(String, Level)>> {
+        if let Some(operation) = self.get_in_progress_operation() {
+            return Ok(Some((format!("{operation} in progress"), Level::Warn)));
+        }
+
+        let branch = get_current_branch(&self.git_repo)?;
+        self.detect_branch_switch(branch.as_deref());
+
+        let Some(branch) = branch else {
+            return match self.branch_policy.detached_head {
+                DetachedHeadPolicy::Skip => Ok(Some(("HEAD is detached".to_string(), Level::Warn))),
+                DetachedHeadPolicy::Commit => Ok(None),
+                DetachedHeadPolicy::Error => {
+                    Ok(Some(("HEAD is detached".to_string(), Level::Error)))
                 }
-            }),
-        )?;
-        index.write()?;
-        let has_staged_changes = self.has_staged_changes()?;
-        Ok(has_staged_changes)
+            };
+        };
+
+        if self.branch_policy.on_branch_switch == BranchSwitchPolicy::Pause {
+            if let Some(initial_branch) = &self.initial_branch {
+                if initial_branch != &branch {
+                    return Ok(Some((
+                        format!("switched from branch '{initial_branch}' to '{branch}'"),
+                        Level::Warn,
+                    )));
+                }
+            }
+        }
+        Ok(self
+            .branch_policy
+            .check_branch(&branch)
+            .map(|reason| (reason, Level::Warn)))
+    }
+
+    fn detect_branch_switch(&self, branch: Option<&str>) {
+        let mut current_branch = self.current_branch.lock().unwrap();
+        if current_branch.as_deref() != branch {
+            info!(
+                "Switched from {} to {}",
+                describe_branch(current_branch.as_deref()),
+                describe_branch(branch)
+            );
+            *current_branch = branch.map(String::from);
+        }
+    }
+
+    // Returns the git operation that is currently in progress, if any.
+    // Committing on top of e.g. a rebase would corrupt it.
+    fn get_in_progress_operation(&self) -> Option<&'static str> {
+        let operation = match self.git_repo.state() {
+            RepositoryState::Clean => None,
+            RepositoryState::Merge => Some("merge"),
+            RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+                Some("cherry-pick")
+            }
+            RepositoryState::Bisect => Some("bisect"),
+            RepositoryState::Rebase
+            | RepositoryState::RebaseInteractive
+            | RepositoryState::RebaseMerge => Some("rebase"),
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("am"),
+        };
+        if operation.is_some() {
+            return operation;
+        }
+
+        if self.git_repo.path().join("index.lock").exists() {
+            return Some(INDEX_LOCK_HOLDER);
+        }
+        None
     }
 
-    fn has_staged_changes(&self) -> Result<bool> {
-        let statuses = self.git_repo.statuses(None)?;
-        Ok(statuses.iter().any(|entry| {
+    /// Returns true if `path` is the index lock of another git process that paused autocommits
+    /// & the lock was released, so watching it resumes them without further changes.
+    /// Otherwise the index lock is ignored, since e.g. every `git status` of an editor takes it.
+    pub fn is_index_lock_released(&self, path: &Path) -> bool {
+        let index_lock_path = self.git_repo.path().join("index.lock");
+        path == index_lock_path
+            && !index_lock_path.exists()
+            && self.pause_reason.lock().unwrap().as_deref()
+                == Some(format!("{INDEX_LOCK_HOLDER} in progress").as_str())
+    }
+
+    // logs only once per pause reason to avoid flooding the log on every change,
+    // returns true if the reason is new
+    fn pause(&self, reason: String, level: Level) -> bool {
+        let mut pause_reason = self.pause_reason.lock().unwrap();
+        if pause_reason.as_ref() == Some(&reason) {
+            return false;
+        }
+        log!(level, "Pausing autocommits: {reason}");
+        *pause_reason = Some(reason.clone());
+        self.events.emit(Event::Pause { reason });
+        true
+    }
+
+    fn resume(&self) {
+        if let Some(reason) = self.pause_reason.lock().unwrap().take() {
+            info!("Resuming autocommits (no longer blocked by: {reason})");
+        }
+    }
+
+    // Scanning the whole working tree takes seconds in large repositories,
+    // so only the changed paths are staged in between full scans
+    fn get_stage_scope(&self, paths: &[PathBuf]) -> StageScope {
+        // interval commits & requested commits are not triggered by specific paths
+        if paths.is_empty() {
+            return StageScope::All;
+        }
+        // the changes detected while paused were not staged
+        if self.pause_reason.lock().unwrap().is_some() {
+            return StageScope::All;
+        }
+        if self.last_full_scan.lock().unwrap().elapsed() >= self.reconcile_interval {
+            debug!("Reconcile interval elapsed, staging the whole working tree");
+            return StageScope::All;
+        }
+
+        let mut pathspecs = BTreeSet::new();
+        for path in paths {
+            let Ok(relative_path) = path.strip_prefix(&self.repo_path) else {
+                continue;
+            };
+            // events in the git directory signal e.g. a branch switch, there is nothing to stage
+            if relative_path.starts_with(".git") {
+                continue;
+            }
+            match relative_path.to_str() {
+                // libgit2 doesn't match the paths inside a directory whose name contains
+                // glob characters, neither when staging nor in the stage policy's status scan
+                Some(pathspec) if pathspec.contains(['*', '?', '[']) && !self.is_file(path) => {
+                    return StageScope::All;
+                }
+                Some(pathspec) if !pathspec.is_empty() => {
+                    pathspecs.insert(pathspec.to_string());
+                }
+                // the repository itself changed, or the path cannot be used as a pathspec
+                _ => return StageScope::All,
+            }
+        }
+        StageScope::Paths(pathspecs.into_iter().collect())
+    }
+
+    // Returns true if `path` is a file, or was a tracked file before it was deleted
+    fn is_file(&self, path: &Path) -> bool {
+        if path.exists() {
+            return path.is_file();
+        }
+        let Ok(relative_path) = path.strip_prefix(&self.repo_path) else {
+            return false;
+        };
+        self.git_repo
+            .index()
+            .is_ok_and(|index| index.get_path(relative_path, 0).is_some())
+    }
+
+    // Returns the changes staged in the index, compared to HEAD
+    fn stage_changes(&self, scope: &StageScope) -> Result<StagedChanges> {
+        let start = Instant::now();
+        let excluded_paths = self.get_excluded_paths(scope)?;
+        let mut index = self.git_repo.index()?;
+        // the changed paths may contain glob characters like `[`, see `StageScope::contains`
+        let (pathspecs, add_option) = match scope {
+            StageScope::All => (vec!["*".to_string()], IndexAddOption::DEFAULT),
+            StageScope::Paths(paths) => (paths.clone(), IndexAddOption::DISABLE_PATHSPEC_MATCH),
+        };
+        // an empty pathspec would match the whole working tree
+        if !pathspecs.is_empty() {
+            let mut is_index_changed = false;
+            index.add_all(
+                pathspecs.iter(),
+                add_option,
+                Some(&mut |path, _matched_spec| {
+                    if self.is_path_ignored(path)
+                        || excluded_paths.contains(path)
+                        || !scope.contains(path)
+                    {
+                        1
+                    } else {
+                        is_index_changed = true;
+                        0
+                    }
+                }),
+            )?;
+            // an unchanged index is not written, so staging nothing leaves the git directory untouched
+            if is_index_changed {
+                index.write()?;
+            }
+        }
+        let staged_changes = self.get_staged_changes()?;
+        debug!("Staged {scope} in {}ms", start.elapsed().as_millis());
+        Ok(staged_changes)
+    }
+
+    // Returns the changed paths that must not be staged according to the stage policy
+    fn get_excluded_paths(&self, scope: &StageScope) -> Result<HashSet<PathBuf>> {
+        if self.stage_policy.stages_all() {
+            return Ok(HashSet::new());
+        }
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true);
+        options.recurse_untracked_dirs(true);
+        options.renames_index_to_workdir(true);
+        if let StageScope::Paths(paths) = scope {
+            if paths.is_empty() {
+                return Ok(HashSet::new());
+            }
+            for path in paths {
+                options.pathspec(path);
+            }
+            options.disable_pathspec_match(true);
+        }
+        let statuses = self.git_repo.statuses(Some(&mut options))?;
+
+        let mut excluded_paths = HashSet::new();
+        for entry in statuses
+            .iter()
+            .filter(|entry| !self.stage_policy.allows(entry.status()))
+        {
+            // a rename consists of a deleted and an added path
+            if let Some(delta) = entry.index_to_workdir() {
+                excluded_paths.extend(delta.old_file().path().map(Path::to_path_buf));
+                excluded_paths.extend(delta.new_file().path().map(Path::to_path_buf));
+            }
+        }
+        trace!("Paths excluded by stage policy: {excluded_paths:?}");
+        Ok(excluded_paths)
+    }
+
+    // Compares the index to HEAD only, which does not scan the working tree
+    fn get_staged_changes(&self) -> Result<StagedChanges> {
+        let statuses = self.get_index_statuses()?;
+        let mut staged_changes = StagedChanges::default();
+        for entry in statuses.iter() {
             let status = entry.status();
-            status.is_index_new()
+            if status.is_index_new() {
+                staged_changes.added += 1;
+            }
+            if status.is_index_deleted() {
+                staged_changes.deleted += 1;
+            }
+            if status.is_index_new()
                 || status.is_index_modified()
                 || status.is_index_deleted()
                 || status.is_index_renamed()
                 || status.is_index_typechange()
-        }))
+            {
+                staged_changes.total += 1;
+            }
+        }
+        // number of tracked files before the staged changes
+        staged_changes.tracked =
+            self.git_repo.index()?.len() + staged_changes.deleted - staged_changes.added;
+        Ok(staged_changes)
+    }
+
+    // Returns a pause reason if a change deletes more files than allowed,
+    // e.g. due to an accidental `rm -r`, a failed sync or an unmounted directory.
+    // The deletions stay staged, so committing them manually confirms them.
+    fn check_mass_deletion(&self, staged_changes: &StagedChanges) -> Option<String> {
+        let deleted = staged_changes.deleted;
+        if deleted == 0 {
+            return None;
+        }
+        let tracked = staged_changes.tracked.max(deleted);
+        let deleted_percent = deleted as f64 * 100.0 / tracked as f64;
+
+        let exceeds_max_deletions = self
+            .stage_policy
+            .max_deletions
+            .is_some_and(|max_deletions| deleted > max_deletions);
+        let exceeds_max_percent = self
+            .stage_policy
+            .max_deletion_percent
+            .is_some_and(|max_percent| deleted_percent > max_percent);
+        if !exceeds_max_deletions && !exceeds_max_percent {
+            return None;
+        }
+        // confirmed by resuming or requesting a commit, via signal or the control socket
+        if self.state.take_mass_deletion_confirmation() {
+            warn!("Committing {deleted} of {tracked} tracked files ({deleted_percent:.0}%) as deleted, as confirmed");
+            return None;
+        }
+
+        Some(format!(
+            "MASS DELETION DETECTED: {deleted} of {tracked} tracked files ({deleted_percent:.0}%) were deleted. Restore the files, or resume autocommits or commit to confirm the deletions"
+        ))
     }
 
     fn log_pending_commit(&self) -> Result<()> {
@@ -115,32 +527,63 @@ impl GitwatchRepo {
         Ok(())
     }
 
-    fn commit_and_push(&self) -> Result<()> {
+    fn commit_and_push(&self, staged_changes: &StagedChanges) -> Result<Option<Oid>> {
+        let start = Instant::now();
         let index = self.git_repo.index()?;
         if index.is_empty() {
             debug!("Index is empty");
-            return Ok(());
+            return Ok(None);
         }
 
         let commit_message = self
             .generate_commit_message()
             .context("Failed to generate commit message")?;
+
+        self.run_hook("pre-commit", &[], None)?;
+        let commit_message = self.run_commit_msg_hook(commit_message)?;
+        let staged_files = self.get_staged_file_paths()?;
+
         let commit_id = self
             .create_git_commit(&commit_message)
             .context("Creating git commit failed")?;
+        self.state.set_next_commit_message(None);
+        self.state.record_commit();
         let commit_short_hash = &commit_id.to_string()[..7];
         info!(
+            event = "commit",
+            commit_id:% = commit_id,
+            files = staged_changes.total,
+            duration_ms = start.elapsed().as_millis() as u64;
             "Created commit '[{}] {}'",
             commit_short_hash,
             commit_message.lines().next().unwrap()
         );
 
+        self.events.emit(Event::Commit {
+            commit_id: commit_id.to_string(),
+            files: staged_files,
+            message: commit_message.clone(),
+        });
+
+        // like git, ignore the exit status of the post-commit hook
+        if let Err(e) = self.run_hook("post-commit", &[], None) {
+            warn!("{e:#}");
+        }
+
         if let Some(remote) = &self.remote {
-            self.push_changes(remote)
-                .context(format!("Failed to push to remote '{remote}'"))?;
+            if self.git_repo.head_detached()? {
+                info!("Skipping push, HEAD is detached");
+            } else if let Err(e) = self.push_changes(remote) {
+                self.state.metrics().record_push_failure();
+                self.events.emit(Event::PushFailure {
+                    error: format!("{e:#}"),
+                    remote: remote.clone(),
+                });
+                return Err(e).context(format!("Failed to push to remote '{remote}'"));
+            }
         }
 
-        Ok(())
+        Ok(Some(commit_id))
     }
 
     fn is_path_ignored(&self, path: &Path) -> bool {
@@ -151,28 +594,59 @@ impl GitwatchRepo {
     }
 
     fn generate_commit_message(&self) -> Result<String> {
-        if let Some(message) = &self.commit_message {
-            Ok(message.clone())
-        } else {
-            // can unwrap safely, because it has been validated that either message or message script is set
-            let script_path = self.commit_message_script.as_ref().unwrap();
-            let commit_message = generate_commit_message(script_path, &self.repo_path)?;
-            Ok(commit_message)
+        if let Some(message) = self.state.next_commit_message() {
+            debug!("Using one-off commit message");
+            return Ok(message);
         }
+        let provider = self
+            .commit_message_provider
+            .as_ref()
+            .context("Neither a commit message nor a commit message provider is set")?;
+        let changes = self.get_change_set(provider.reads_changes())?;
+
+        let start = Instant::now();
+        let commit_message = provider.commit_message(&changes);
+        if self.script_message_active {
+            self.state.metrics().record_script_duration(start.elapsed());
+        }
+        commit_message
+    }
+
+    // collecting the staged files requires a status scan, so it is skipped if they aren't needed
+    fn get_change_set(&self, include_changes: bool) -> Result<ChangeSet> {
+        let changes = if include_changes {
+            let statuses = self.get_index_statuses()?;
+            statuses
+                .iter()
+                .filter_map(|entry| {
+                    let status = ChangeStatus::from_index_status(entry.status())?;
+                    let path = entry.path().ok()?.to_string();
+                    Some(Change { path, status })
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(ChangeSet {
+            branch: get_current_branch(&self.git_repo)?,
+            changes,
+            repository: self.repo_path.clone(),
+        })
     }
 
     fn get_staged_file_paths(&self) -> Result<Vec<String>> {
-        let statuses = self.get_statuses()?;
-        let staged_file_paths = statuses
-            .iter()
-            .filter(|entry| entry.status().is_index_new() || entry.status().is_index_modified())
-            .filter_map(|entry| entry.path().ok().map(|p| p.to_string()))
-            .collect();
-        Ok(staged_file_paths)
+        let change_set = self.get_change_set(true)?;
+        Ok(change_set
+            .changes
+            .into_iter()
+            .map(|change| change.path)
+            .collect())
     }
 
     fn create_git_commit(&self, commit_message: &str) -> Result<Oid> {
         let mut index = self.git_repo.index()?;
+        // hooks may have modified the index on disk
+        index.read(false)?;
         let tree_oid = index.write_tree()?;
         let tree = self.git_repo.find_tree(tree_oid)?;
 
@@ -204,6 +678,14 @@ impl GitwatchRepo {
             .context("Failed to read git status")
     }
 
+    fn get_index_statuses(&self) -> Result<git2::Statuses<'_>> {
+        let mut options = StatusOptions::new();
+        options.show(StatusShow::Index);
+        self.git_repo
+            .statuses(Some(&mut options))
+            .context("Failed to read git status")
+    }
+
     fn log_status(&self) -> Result<()> {
         if let Ok(head) = self.git_repo.head() {
             if let Ok(commit) = head.peel_to_commit() {
@@ -243,6 +725,7 @@ impl GitwatchRepo {
     }
 
     fn push_changes(&self, remote_name: &str) -> Result<()> {
+        let start = Instant::now();
         debug!("Pushing to remote {remote_name}");
         let mut remote = self.git_repo.find_remote(remote_name)?;
 
@@ -250,22 +733,79 @@ impl GitwatchRepo {
         let refspec = self.get_current_refspec()?;
         trace!("Pushing refspec: {refspec}");
 
+        if self.run_hooks {
+            let remote_url = remote.url().unwrap_or_default().to_string();
+            let pre_push_input = self.get_pre_push_input(remote_name, &refspec)?;
+            self.run_hook(
+                "pre-push",
+                &[remote_name, &remote_url],
+                Some(&pre_push_input),
+            )?;
+        }
+
         let auth = GitAuthenticator::default();
         auth.push(&self.git_repo, &mut remote, &[&refspec])?;
-        info!("Pushed changes to {remote_name}");
+        self.state.record_push();
+        self.events.emit(Event::Push {
+            remote: remote_name.to_string(),
+        });
+        info!(
+            event = "push",
+            remote = remote_name,
+            duration_ms = start.elapsed().as_millis() as u64;
+            "Pushed changes to {remote_name}"
+        );
         Ok(())
     }
 
+    fn run_hook(&self, name: &str, args: &[&str], stdin: Option<&str>) -> Result<()> {
+        if !self.run_hooks {
+            return Ok(());
+        }
+        GitHooks::new(&self.git_repo)?.run(name, args, stdin)
+    }
+
+    // passes the commit message through the commit-msg hook, which may rewrite it
+    fn run_commit_msg_hook(&self, commit_message: String) -> Result<String> {
+        if !self.run_hooks {
+            return Ok(commit_message);
+        }
+        let message_file = self.git_repo.path().join("COMMIT_EDITMSG");
+        fs::write(&message_file, &commit_message).context("Failed to write commit message file")?;
+        self.run_hook("commit-msg", &[&message_file.to_string_lossy()], None)?;
+        let commit_message =
+            fs::read_to_string(&message_file).context("Failed to read commit message file")?;
+        if commit_message.trim().is_empty() {
+            bail!("Commit message is empty after running commit-msg hook");
+        }
+        Ok(commit_message)
+    }
+
+    // builds the '<local ref> <local sha> <remote ref> <remote sha>' line git passes to pre-push
+    fn get_pre_push_input(&self, remote_name: &str, refspec: &str) -> Result<String> {
+        let head = self.git_repo.head()?;
+        // the pushed branch, e.g. `refs/heads/main`, which may differ from the remote ref
+        let local_ref = head.name().context("HEAD is not valid UTF-8")?.to_string();
+        let local_oid = head.peel_to_commit()?.id();
+        let remote_ref = refspec
+            .split_once(':')
+            .map(|(_, dst)| dst)
+            .unwrap_or(refspec);
+        let branch_name = remote_ref.trim_start_matches("refs/heads/");
+        let remote_oid = self
+            .git_repo
+            .refname_to_id(&format!("refs/remotes/{remote_name}/{branch_name}"))
+            .unwrap_or(Oid::ZERO_SHA1);
+        Ok(format!(
+            "{local_ref} {local_oid} {remote_ref} {remote_oid}\n"
+        ))
+    }
+
     #[cfg(not(tarpaulin_include))]
     fn get_current_refspec(&self) -> Result<String> {
-        const ERROR: &str = "Failed to parse refspec";
-        let branch_name = self
-            .git_repo
-            .head()
-            .context(ERROR)?
-            .shorthand()
-            .context(ERROR)?
-            .to_string();
+        let branch_name = get_current_branch(&self.git_repo)
+            .context("Failed to parse refspec")?
+            .context("Cannot push a detached HEAD")?;
         Ok(format!("HEAD:refs/heads/{branch_name}"))
     }
 
@@ -288,6 +828,22 @@ impl GitwatchRepo {
     }
 }
 
+// Returns the name of the checked out branch, or None if HEAD is detached.
+// Unlike `Repository::head`, this also works for unborn branches.
+pub fn get_current_branch(repo: &Repository) -> Result<Option<String>> {
+    let head = repo.find_reference("HEAD")?;
+    Ok(head
+        .symbolic_target()?
+        .map(|target| target.trim_start_matches("refs/heads/").to_string()))
+}
+
+fn describe_branch(branch: Option<&str>) -> String {
+    match branch {
+        Some(branch) => format!("branch '{branch}'"),
+        None => "detached HEAD".to_string(),
+    }
+}
+
 #[cfg(not(tarpaulin_include))]
 impl Display for GitwatchRepo {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -297,12 +853,20 @@ impl Display for GitwatchRepo {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, sync::Once};
+    use std::{
+        fs,
+        sync::{mpsc, Once},
+    };
 
     use tempfile::TempDir;
     use testresult::TestResult;
 
-    use crate::{cli::LogLevel, logger::setup_logger};
+    use crate::{
+        cli::LogLevel,
+        control::{handle_request, ControlCommand, ControlRequest},
+        hooks::create_hook,
+        logger::setup_logger,
+    };
 
     use super::*;
 
@@ -349,14 +913,11 @@ mod tests {
     #[test]
     fn test_empty_repo() -> TestResult {
         let temp_dir = tempfile::tempdir()?;
-        let result = GitwatchRepo::new(
-            temp_dir.path(),
-            Some("test".to_string()),
-            None,
-            None,
-            false,
-            None,
-        );
+        let result = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            ..AppConfig::default()
+        });
         assert!(result.is_err());
         let err_str = result.err().unwrap().to_string();
         assert!(
@@ -370,14 +931,11 @@ mod tests {
     fn test_invalid_commit_message_script() -> TestResult {
         let temp_dir = init_test_repo()?;
 
-        let result = GitwatchRepo::new(
-            temp_dir.path(),
-            None,
-            Some(PathBuf::from("/nonexistent/script")),
-            None,
-            false,
-            None,
-        );
+        let result = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message_script: Some(PathBuf::from("/nonexistent/script")),
+            ..AppConfig::default()
+        });
         assert!(result.is_err());
         let err = result.err().unwrap();
         assert!(err.to_string().contains("Commit message script not found"));
@@ -388,14 +946,12 @@ mod tests {
     fn test_invalid_remote() -> TestResult {
         let temp_dir = init_test_repo()?;
 
-        let result = GitwatchRepo::new(
-            temp_dir.path(),
-            Some("test".to_string()),
-            None,
-            None,
-            false,
-            Some("nonexistent-remote".to_string()),
-        );
+        let result = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            remote: Some("nonexistent-remote".to_string()),
+            ..AppConfig::default()
+        });
         assert!(result.is_err());
         let err_str = result.err().unwrap().to_string();
         assert!(
@@ -406,19 +962,59 @@ mod tests {
     }
 
     #[test]
-    fn test_commit_and_push() -> TestResult {
+    fn test_script_duration_metric() -> TestResult {
         let temp_dir = init_test_repo()?;
-        let repo = GitwatchRepo::new(
+        let script_path = create_hook(
             temp_dir.path(),
-            Some("test".to_string()),
-            None,
-            None,
-            false,
-            Some("origin".to_string()),
+            "message.sh",
+            "echo 'Script message'",
+            0o755,
         )?;
+        let config = AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message_script: Some(script_path),
+            ..AppConfig::default()
+        };
+        let script_count = |repo: &GitwatchRepo| {
+            let metrics = repo.state.metrics().render(temp_dir.path(), false);
+            metrics
+                .lines()
+                .find(|line| line.starts_with("gitwatch_script_duration_seconds_count"))
+                .and_then(|line| line.rsplit(' ').next())
+                .map(str::to_string)
+        };
+
+        let repo = GitwatchRepo::new(&config)?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        fs::write(temp_dir.path().join("foo.txt"), "script")?;
+        repo.process_changes(&[])?;
+        assert_eq!(script_count(&repo).as_deref(), Some("1"));
+
+        // a custom provider replaces the script
+        let repo = GitwatchRepo::new(&config)?
+            .with_commit_message_provider(Arc::new(StaticMessage("Static message".to_string())));
+        fs::write(temp_dir.path().join("foo.txt"), "static")?;
+        repo.process_changes(&[])?;
+        assert_eq!(
+            repo.git_repo.head()?.peel_to_commit()?.message()?,
+            "Static message"
+        );
+        assert_eq!(script_count(&repo).as_deref(), Some("0"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_and_push() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            remote: Some("origin".to_string()),
+            ..AppConfig::default()
+        })?;
 
         // commit with empty index
-        repo.commit_and_push()?;
+        repo.commit_and_push(&StagedChanges::default())?;
 
         // verify no commit was created
         let head = repo.git_repo.head();
@@ -441,21 +1037,498 @@ mod tests {
     #[test]
     fn test_ignore_regex() -> Result<()> {
         let temp_dir = init_test_repo()?;
-        let repo = GitwatchRepo::new(
-            temp_dir.path(),
-            Some("test".to_string()),
-            None,
-            Some(Regex::new(".*foo.txt.*")?),
-            false,
-            None,
-        )?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            ignore_regex: Some(Regex::new(".*foo.txt.*")?),
+            ..AppConfig::default()
+        })?;
 
         fs::write(temp_dir.path().join("foo.txt"), "test content")?;
-        let has_staged_changes = repo.stage_changes()?;
-        assert!(
-            !has_staged_changes,
+        let staged_changes = repo.stage_changes(&StageScope::All)?;
+        assert_eq!(
+            staged_changes.total, 0,
             "Index should be empty when file is ignored"
         );
         Ok(())
     }
+
+    #[test]
+    fn test_pre_push_input() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            ..AppConfig::default()
+        })?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        let head = repo.git_repo.head()?;
+        let local_ref = head.name().unwrap();
+        let local_oid = head.peel_to_commit()?.id();
+
+        // the remote branch may be named differently than the local one
+        let input = repo.get_pre_push_input("origin", "HEAD:refs/heads/upstream")?;
+        assert_eq!(
+            input,
+            format!(
+                "{local_ref} {local_oid} refs/heads/upstream {}\n",
+                Oid::ZERO_SHA1
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_commit_hook_aborts_commit() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            run_hooks: true,
+            ..AppConfig::default()
+        })?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        create_hook(
+            &repo.git_repo.path().join("hooks"),
+            "pre-commit",
+            "exit 1",
+            0o755,
+        )?;
+
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+        let result = repo.process_changes(&[]);
+        assert!(result.is_err());
+        assert_eq!(
+            repo.git_repo.head()?.peel_to_commit()?.message()?,
+            "feat: initial commit"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_commit_msg_hook_rewrites_message() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            run_hooks: true,
+            ..AppConfig::default()
+        })?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        create_hook(
+            &repo.git_repo.path().join("hooks"),
+            "commit-msg",
+            "echo 'rewritten' > \"$1\"",
+            0o755,
+        )?;
+        create_hook(
+            &repo.git_repo.path().join("hooks"),
+            "post-commit",
+            "touch post-commit-ran",
+            0o755,
+        )?;
+
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+        repo.process_changes(&[])?;
+
+        let head_commit = repo.git_repo.head()?.peel_to_commit()?;
+        assert_eq!(head_commit.message()?, "rewritten\n");
+        assert!(temp_dir.path().join("post-commit-ran").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_progress_operation() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            ..AppConfig::default()
+        })?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        let head_id = repo.git_repo.head()?.peel_to_commit()?.id();
+
+        // simulate an in-progress merge
+        let merge_head_path = repo.git_repo.path().join("MERGE_HEAD");
+        fs::write(&merge_head_path, format!("{head_id}\n"))?;
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+        assert_eq!(repo.process_changes(&[])?, None);
+        assert_eq!(repo.git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert_eq!(
+            repo.pause_reason.lock().unwrap().as_deref(),
+            Some("merge in progress")
+        );
+
+        // the index lock is only watched while it pauses autocommits
+        let index_lock_path = repo.git_repo.path().join("index.lock");
+        assert!(!repo.is_index_lock_released(&index_lock_path));
+
+        // simulate a concurrent git process
+        fs::remove_file(&merge_head_path)?;
+        fs::write(&index_lock_path, "")?;
+        repo.process_changes(&[])?;
+        assert_eq!(repo.git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(!repo.is_index_lock_released(&index_lock_path));
+
+        fs::remove_file(&index_lock_path)?;
+        assert!(repo.is_index_lock_released(&index_lock_path));
+        let commit_id = repo.process_changes(&[])?;
+        assert_eq!(
+            commit_id,
+            Some(repo.git_repo.head()?.peel_to_commit()?.id())
+        );
+        assert_ne!(commit_id, Some(head_id));
+        assert!(repo.pause_reason.lock().unwrap().is_none());
+        assert!(!repo.is_index_lock_released(&index_lock_path));
+        Ok(())
+    }
+
+    #[test]
+    fn test_detached_head() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let config = AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            remote: Some("origin".to_string()),
+            ..AppConfig::default()
+        };
+        let repo = GitwatchRepo::new(&config)?;
+        create_initial_commit(temp_dir.path(), &repo.git_repo)?;
+        let head_id = repo.git_repo.head()?.peel_to_commit()?.id();
+        repo.git_repo.set_head_detached(head_id)?;
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+
+        // skip by default
+        repo.process_changes(&[])?;
+        assert_eq!(repo.git_repo.head()?.peel_to_commit()?.id(), head_id);
+
+        let error_repo = GitwatchRepo::new(&AppConfig {
+            branch_policy: BranchPolicy {
+                detached_head: DetachedHeadPolicy::Error,
+                ..BranchPolicy::default()
+            },
+            ..config.clone()
+        })?;
+        // pauses instead of failing, which would end the watch loop
+        error_repo.process_changes(&[])?;
+        assert_eq!(
+            error_repo.pause_reason.lock().unwrap().as_deref(),
+            Some("HEAD is detached")
+        );
+        assert_eq!(repo.git_repo.head()?.peel_to_commit()?.id(), head_id);
+
+        // commits, but skips pushing to the (unreachable) remote
+        let commit_repo = GitwatchRepo::new(&AppConfig {
+            branch_policy: BranchPolicy {
+                detached_head: DetachedHeadPolicy::Commit,
+                ..BranchPolicy::default()
+            },
+            ..config
+        })?;
+        commit_repo.process_changes(&[])?;
+        assert_ne!(repo.git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(repo.git_repo.head_detached()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_denied_branch() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        let branch = get_current_branch(&git_repo)?.unwrap();
+        let head_id = git_repo.head()?.peel_to_commit()?.id();
+
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            branch_policy: BranchPolicy {
+                denied_branches: vec![branch.clone()],
+                ..BranchPolicy::default()
+            },
+            ..AppConfig::default()
+        })?;
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+        repo.process_changes(&[])?;
+
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert_eq!(
+            repo.pause_reason.lock().unwrap().clone(),
+            Some(format!("branch '{branch}' is denied"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_branch_switch_pause() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        let initial_branch = get_current_branch(&git_repo)?.unwrap();
+        let head_commit = git_repo.head()?.peel_to_commit()?;
+
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            branch_policy: BranchPolicy {
+                on_branch_switch: BranchSwitchPolicy::Pause,
+                ..BranchPolicy::default()
+            },
+            ..AppConfig::default()
+        })?;
+
+        git_repo.branch("other", &head_commit, false)?;
+        git_repo.set_head("refs/heads/other")?;
+        fs::write(temp_dir.path().join("foo.txt"), "test content")?;
+        repo.process_changes(&[])?;
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_commit.id());
+        assert_eq!(
+            repo.current_branch.lock().unwrap().as_deref(),
+            Some("other")
+        );
+
+        // switching back resumes autocommits
+        git_repo.set_head(&format!("refs/heads/{initial_branch}"))?;
+        repo.process_changes(&[])?;
+        assert_ne!(git_repo.head()?.peel_to_commit()?.id(), head_commit.id());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stage_policy() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        fs::write(temp_dir.path().join("tracked.txt"), "tracked")?;
+
+        let config = AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            ..AppConfig::default()
+        };
+        GitwatchRepo::new(&config)?.process_changes(&[])?;
+
+        let repo = GitwatchRepo::new(&AppConfig {
+            stage_policy: StagePolicy {
+                deleted: false,
+                untracked: false,
+                ..StagePolicy::default()
+            },
+            ..config
+        })?;
+        fs::write(temp_dir.path().join("new.txt"), "new")?;
+        fs::write(temp_dir.path().join("tracked.txt"), "modified")?;
+        fs::remove_file(temp_dir.path().join("initial.txt"))?;
+        repo.process_changes(&[])?;
+
+        let tree = git_repo.head()?.peel_to_tree()?;
+        assert!(tree.get_path(Path::new("new.txt")).is_err());
+        assert!(tree.get_path(Path::new("initial.txt")).is_ok());
+        let tracked_entry = tree.get_path(Path::new("tracked.txt"))?;
+        let tracked_blob = git_repo.find_blob(tracked_entry.id())?;
+        assert_eq!(tracked_blob.content(), b"modified");
+        Ok(())
+    }
+
+    #[test]
+    fn test_targeted_staging() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let path = temp_dir.path();
+        let git_repo = Repository::open(path)?;
+        create_initial_commit(path, &git_repo)?;
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: path.to_path_buf(),
+            commit_message: Some("test".to_string()),
+            reconcile_interval_seconds: 600,
+            ..AppConfig::default()
+        })?;
+        let committed_paths = || -> Result<Vec<String>> {
+            let mut paths = Vec::new();
+            git_repo.head()?.peel_to_tree()?.walk(
+                git2::TreeWalkMode::PreOrder,
+                |root, entry| {
+                    if entry.kind() == Some(git2::ObjectType::Blob) {
+                        paths.push(format!("{root}{}", entry.name().unwrap()));
+                    }
+                    git2::TreeWalkResult::Ok
+                },
+            )?;
+            Ok(paths)
+        };
+
+        fs::create_dir(path.join("dir"))?;
+        fs::write(path.join("dir/nested.txt"), "nested")?;
+        fs::write(path.join("file[1].txt"), "glob")?;
+        fs::write(path.join("file1.txt"), "matched by the glob")?;
+        fs::write(path.join("unrelated.txt"), "no event")?;
+
+        // a directory matches the paths inside it, glob characters are matched literally
+        repo.process_changes(&[path.join("dir"), path.join("file[1].txt")])?;
+        assert_eq!(
+            committed_paths()?,
+            vec!["dir/nested.txt", "file[1].txt", "initial.txt"]
+        );
+
+        // deleted directories are staged as well
+        fs::remove_dir_all(path.join("dir"))?;
+        repo.process_changes(&[path.join("dir")])?;
+        assert_eq!(committed_paths()?, vec!["file[1].txt", "initial.txt"]);
+
+        // deletions with glob characters don't stage the paths matched by the glob either
+        fs::remove_file(path.join("file[1].txt"))?;
+        repo.process_changes(&[path.join("file[1].txt")])?;
+        assert_eq!(committed_paths()?, vec!["initial.txt"]);
+
+        // a full scan catches changes without events
+        repo.process_changes(&[])?;
+        assert_eq!(
+            committed_paths()?,
+            vec!["file1.txt", "initial.txt", "unrelated.txt"]
+        );
+
+        // the paths inside a directory with glob characters are staged by a full scan
+        fs::create_dir(path.join("dir[1]"))?;
+        fs::write(path.join("dir[1]/nested.txt"), "nested")?;
+        repo.process_changes(&[path.join("dir[1]")])?;
+        assert_eq!(
+            committed_paths()?,
+            vec![
+                "dir[1]/nested.txt",
+                "file1.txt",
+                "initial.txt",
+                "unrelated.txt"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_deletions() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        fs::write(temp_dir.path().join("foo.txt"), "foo")?;
+
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            stage_policy: StagePolicy {
+                max_deletions: Some(1),
+                ..StagePolicy::default()
+            },
+            ..AppConfig::default()
+        })?;
+        repo.process_changes(&[])?;
+        let head_id = git_repo.head()?.peel_to_commit()?.id();
+
+        fs::remove_file(temp_dir.path().join("foo.txt"))?;
+        fs::remove_file(temp_dir.path().join("initial.txt"))?;
+        repo.process_changes(&[])?;
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(repo
+            .pause_reason
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|reason| reason.contains("2 of 2 tracked files")));
+
+        // restoring one of the files resumes autocommits
+        fs::write(temp_dir.path().join("foo.txt"), "foo")?;
+        repo.process_changes(&[])?;
+        assert_ne!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(repo.pause_reason.lock().unwrap().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_confirm_mass_deletion() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        let state = Arc::new(AppState::new(temp_dir.path().to_path_buf()));
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            stage_policy: StagePolicy {
+                max_deletions: Some(0),
+                ..StagePolicy::default()
+            },
+            ..AppConfig::default()
+        })?
+        .with_state(state.clone());
+        fs::write(temp_dir.path().join("foo.txt"), "foo")?;
+        repo.process_changes(&[])?;
+        let head_id = git_repo.head()?.peel_to_commit()?.id();
+
+        fs::remove_file(temp_dir.path().join("foo.txt"))?;
+        repo.process_changes(&[])?;
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(state.is_mass_deletion_paused());
+
+        // resuming via the control socket commits the held back deletions once
+        let (control_tx, control_rx) = mpsc::channel();
+        let response = handle_request(ControlRequest::Resume, &control_tx, &state);
+        assert!(response.ok);
+        assert_eq!(control_rx.try_recv(), Ok(ControlCommand::Wake));
+        repo.process_changes(&[])?;
+        assert_ne!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(repo.pause_reason.lock().unwrap().is_none());
+        assert!(!state.is_mass_deletion_paused());
+        assert!(!state.take_mass_deletion_confirmation());
+
+        // a resume without a mass deletion doesn't confirm the next one
+        handle_request(ControlRequest::Resume, &control_tx, &state);
+        let head_id = git_repo.head()?.peel_to_commit()?.id();
+        fs::remove_file(temp_dir.path().join("initial.txt"))?;
+        fs::write(temp_dir.path().join("bar.txt"), "bar")?;
+        repo.process_changes(&[])?;
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_deletion_percent() -> TestResult {
+        let temp_dir = init_test_repo()?;
+        let git_repo = Repository::open(temp_dir.path())?;
+        create_initial_commit(temp_dir.path(), &git_repo)?;
+        for i in 0..9 {
+            fs::write(temp_dir.path().join(format!("{i}.txt")), "content")?;
+        }
+
+        let repo = GitwatchRepo::new(&AppConfig {
+            repository: temp_dir.path().to_path_buf(),
+            commit_message: Some("test".to_string()),
+            stage_policy: StagePolicy {
+                max_deletion_percent: Some(25.0),
+                ..StagePolicy::default()
+            },
+            ..AppConfig::default()
+        })?;
+        repo.process_changes(&[])?;
+
+        // deleting 2 of 10 files is below the threshold
+        fs::remove_file(temp_dir.path().join("0.txt"))?;
+        fs::remove_file(temp_dir.path().join("1.txt"))?;
+        repo.process_changes(&[])?;
+        let head_id = git_repo.head()?.peel_to_commit()?.id();
+        assert!(git_repo
+            .head()?
+            .peel_to_tree()?
+            .get_path(Path::new("0.txt"))
+            .is_err());
+
+        // deleting 3 of the remaining 8 files is above the threshold
+        for i in 2..5 {
+            fs::remove_file(temp_dir.path().join(format!("{i}.txt")))?;
+        }
+        repo.process_changes(&[])?;
+        assert_eq!(git_repo.head()?.peel_to_commit()?.id(), head_id);
+        assert!(repo
+            .pause_reason
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|reason| reason.contains("3 of 8 tracked files (38%)")));
+        Ok(())
+    }
 }
@@ -0,0 +1,193 @@
+use std::{
+    env,
+    os::unix::net::{SocketAddr, UnixDatagram},
+    process,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+
+/// Sends notifications to systemd if gitwatch runs as a `Type=notify` service, see sd_notify(3).
+/// All notifications are no-ops otherwise.
+pub struct Notifier {
+    address: SocketAddr,
+    socket: UnixDatagram,
+    watchdog: Option<Watchdog>,
+}
+
+struct Watchdog {
+    // half of the interval configured via `WatchdogSec=`, as recommended by systemd
+    interval: Duration,
+    last_ping: Mutex<Option<Instant>>,
+}
+
+static NOTIFIER: OnceLock<Option<Notifier>> = OnceLock::new();
+
+/// Tells systemd that gitwatch is watching for changes
+pub fn ready() {
+    notify("READY=1");
+}
+
+/// Tells systemd that the configuration is being reloaded, followed by `ready` once done
+pub fn reloading() {
+    notify("RELOADING=1");
+}
+
+/// Tells systemd that gitwatch commits its remaining changes & exits
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Pings the systemd watchdog, if enabled.
+/// Can be called as often as needed, pings are sent at most every half watchdog interval.
+pub fn watchdog() {
+    if let Some(notifier) = notifier() {
+        notifier.watchdog();
+    }
+}
+
+/// The interval in which `watchdog` has to be called, if the watchdog is enabled
+pub fn watchdog_interval() -> Option<Duration> {
+    notifier()?
+        .watchdog
+        .as_ref()
+        .map(|watchdog| watchdog.interval)
+}
+
+fn notify(state: &str) {
+    if let Some(notifier) = notifier() {
+        notifier.send(state);
+    }
+}
+
+fn notifier() -> Option<&'static Notifier> {
+    NOTIFIER
+        .get_or_init(|| match Notifier::from_env() {
+            Ok(notifier) => notifier,
+            Err(e) => {
+                warn!("Failed to set up systemd notifications: {e:#}");
+                None
+            }
+        })
+        .as_ref()
+}
+
+impl Notifier {
+    /// Returns None if `$NOTIFY_SOCKET` is not set
+    fn from_env() -> Result<Option<Self>> {
+        let Some(socket_path) = env::var_os("NOTIFY_SOCKET") else {
+            return Ok(None);
+        };
+        let socket_path = socket_path.to_string_lossy().to_string();
+        let watchdog_interval = get_watchdog_interval(
+            env::var("WATCHDOG_USEC").ok().as_deref(),
+            env::var("WATCHDOG_PID").ok().as_deref(),
+        );
+        Self::new(&socket_path, watchdog_interval).map(Some)
+    }
+
+    fn new(socket_path: &str, watchdog_interval: Option<Duration>) -> Result<Self> {
+        let address = parse_address(socket_path)
+            .with_context(|| format!("Invalid notify socket '{socket_path}'"))?;
+        debug!("Sending systemd notifications to {socket_path}");
+        Ok(Self {
+            address,
+            socket: UnixDatagram::unbound()?,
+            watchdog: watchdog_interval.map(|interval| Watchdog {
+                interval,
+                last_ping: Mutex::new(None),
+            }),
+        })
+    }
+
+    fn send(&self, state: &str) {
+        if let Err(e) = self.socket.send_to_addr(state.as_bytes(), &self.address) {
+            warn!("Failed to notify systemd ({state}): {e}");
+        }
+    }
+
+    fn watchdog(&self) {
+        let Some(watchdog) = &self.watchdog else {
+            return;
+        };
+        let mut last_ping = watchdog.last_ping.lock().unwrap();
+        if last_ping.is_some_and(|last_ping| last_ping.elapsed() < watchdog.interval) {
+            return;
+        }
+        self.send("WATCHDOG=1");
+        *last_ping = Some(Instant::now());
+    }
+}
+
+// socket paths starting with '@' refer to the abstract namespace
+fn parse_address(socket_path: &str) -> Result<SocketAddr> {
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket_path.strip_prefix('@') {
+        use std::os::linux::net::SocketAddrExt;
+        return Ok(SocketAddr::from_abstract_name(name)?);
+    }
+    Ok(SocketAddr::from_pathname(socket_path)?)
+}
+
+// Returns the interval in which the watchdog has to be pinged, if it is enabled for this process
+fn get_watchdog_interval(usec: Option<&str>, pid: Option<&str>) -> Option<Duration> {
+    // the watchdog may be meant for another process, e.g. a wrapper script
+    if pid.is_some_and(|pid| pid.parse() != Ok(process::id())) {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_get_watchdog_interval() {
+        let pid = process::id().to_string();
+        assert_eq!(
+            get_watchdog_interval(Some("30000000"), None),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            get_watchdog_interval(Some("30000000"), Some(&pid)),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(get_watchdog_interval(Some("30000000"), Some("1")), None);
+        assert_eq!(get_watchdog_interval(Some("0"), None), None);
+        assert_eq!(get_watchdog_interval(None, None), None);
+    }
+
+    #[test]
+    fn test_notifications() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let socket_path = temp_dir.path().join("notify.sock");
+        let systemd = UnixDatagram::bind(&socket_path)?;
+        systemd.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let mut buffer = [0; 64];
+        let mut receive = || -> Result<String> {
+            let len = systemd.recv(&mut buffer)?;
+            Ok(String::from_utf8_lossy(&buffer[..len]).to_string())
+        };
+
+        let notifier = Notifier::new(
+            &socket_path.to_string_lossy(),
+            Some(Duration::from_secs(60)),
+        )?;
+        notifier.send("READY=1");
+        assert_eq!(receive()?, "READY=1");
+
+        notifier.watchdog();
+        notifier.watchdog();
+        notifier.send("STOPPING=1");
+        // the second ping is skipped, the interval hasn't elapsed yet
+        assert_eq!(receive()?, "WATCHDOG=1");
+        assert_eq!(receive()?, "STOPPING=1");
+        Ok(())
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    env, fs, iter,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use indoc::formatdoc;
+use log::{info, warn};
+
+use crate::{app_config::AppConfig, cli::CliOptions, util::normalize_path};
+
+/// Manages a `systemd --user` service watching a repository
+#[derive(Clone, Debug, PartialEq, Subcommand)]
+pub enum ServiceCommand {
+    /// Generate the service unit for a repository
+    Install {
+        /// Path to the repository
+        repository: PathBuf,
+
+        /// Options passed to `gitwatch watch`, e.g. --commit-message "Auto commit"
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Start the service now and on login
+    Enable {
+        /// Path to the repository
+        repository: PathBuf,
+    },
+    /// Stop the service and don't start it on login anymore
+    Disable {
+        /// Path to the repository
+        repository: PathBuf,
+    },
+    /// Show the status of the service
+    Status {
+        /// Path to the repository
+        repository: PathBuf,
+    },
+    /// Stop the service and remove its unit
+    Uninstall {
+        /// Path to the repository
+        repository: PathBuf,
+    },
+}
+
+pub fn run(command: ServiceCommand) -> Result<()> {
+    match command {
+        ServiceCommand::Install { repository, args } => install(&repository, &args),
+        ServiceCommand::Enable { repository } => {
+            let unit_name = get_unit_name(&resolve_repository(&repository)?);
+            systemctl(&["enable", "--now", &unit_name])?;
+            info!("Enabled {unit_name}");
+            Ok(())
+        }
+        ServiceCommand::Disable { repository } => {
+            let unit_name = get_unit_name(&resolve_repository(&repository)?);
+            systemctl(&["disable", "--now", &unit_name])?;
+            info!("Disabled {unit_name}");
+            Ok(())
+        }
+        ServiceCommand::Status { repository } => {
+            let unit_name = get_unit_name(&resolve_repository(&repository)?);
+            // systemctl exits with non-zero if the service is not running, which is no error here
+            Command::new("systemctl")
+                .args(["--user", "status", &unit_name])
+                .status()
+                .context("Failed to run systemctl")?;
+            Ok(())
+        }
+        ServiceCommand::Uninstall { repository } => uninstall(&resolve_repository(&repository)?),
+    }
+}
+
+fn install(repository: &Path, args: &[String]) -> Result<()> {
+    // fail now instead of when the service starts
+    let cli_opts = CliOptions::try_parse_from(
+        iter::once("gitwatch watch".into())
+            .chain(iter::once(repository.as_os_str().to_owned()))
+            .chain(args.iter().map(Into::into)),
+    )?;
+    let config = AppConfig::new(cli_opts)?;
+    if !config.watch {
+        bail!("A service has to watch the repository, remove --watch=false");
+    }
+
+    let executable = env::current_exe().context("Failed to determine the gitwatch executable")?;
+    let unit_path = get_unit_dir()?.join(get_unit_name(&config.repository));
+    let unit = render_unit(&executable, &config.repository, args);
+    fs::create_dir_all(unit_path.parent().unwrap())?;
+    fs::write(&unit_path, unit)
+        .with_context(|| format!("Failed to write {}", unit_path.display()))?;
+    info!("Installed {}", unit_path.display());
+
+    // the user manager may not be running, e.g. when installing over ssh
+    if let Err(e) = systemctl(&["daemon-reload"]) {
+        warn!("{e:#}, run `systemctl --user daemon-reload` before enabling the service");
+    }
+    info!(
+        "Start it with `gitwatch service enable {}`",
+        config.repository.display()
+    );
+    Ok(())
+}
+
+fn uninstall(repository: &Path) -> Result<()> {
+    let unit_name = get_unit_name(repository);
+    let unit_path = get_unit_dir()?.join(&unit_name);
+    if !unit_path.exists() {
+        bail!("No service installed for {}", repository.display());
+    }
+    if let Err(e) = systemctl(&["disable", "--now", &unit_name]) {
+        warn!("{e:#}");
+    }
+    fs::remove_file(&unit_path)
+        .with_context(|| format!("Failed to remove {}", unit_path.display()))?;
+    if let Err(e) = systemctl(&["daemon-reload"]) {
+        warn!("{e:#}");
+    }
+    info!("Uninstalled {}", unit_path.display());
+    Ok(())
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()
+        .context("Failed to run systemctl")?;
+    if !status.success() {
+        bail!("`systemctl --user {}` failed ({status})", args.join(" "));
+    }
+    Ok(())
+}
+
+// the repository may not exist anymore when disabling or uninstalling its service
+fn resolve_repository(repository: &Path) -> Result<PathBuf> {
+    normalize_path(repository)
+        .or_else(|_| std::path::absolute(repository))
+        .with_context(|| format!("Invalid repository path '{}'", repository.display()))
+}
+
+fn get_unit_dir() -> Result<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME").context("$HOME is not set")?).join(".config"),
+    };
+    Ok(config_dir.join("systemd").join("user"))
+}
+
+/// Returns the name of the service unit for `repository`, an instance of
+/// `gitwatch@.service`, like `systemctl --user start gitwatch@$(systemd-escape --path <repository>)`
+pub fn get_unit_name(repository: &Path) -> String {
+    format!("gitwatch@{}.service", escape_path(repository))
+}
+
+// equivalent of `systemd-escape --path`
+fn escape_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let components = path
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>();
+    if components.is_empty() {
+        return "-".to_string();
+    }
+
+    let mut escaped = String::new();
+    for (i, byte) in components.join("/").bytes().enumerate() {
+        match byte {
+            b'/' => escaped.push('-'),
+            b'.' if i == 0 => escaped.push_str("\\x2e"),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b':' | b'_' | b'.' => {
+                escaped.push(byte as char)
+            }
+            _ => escaped.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+    escaped
+}
+
+fn render_unit(executable: &Path, repository: &Path, args: &[String]) -> String {
+    let executable = quote_arg(&executable.to_string_lossy());
+    let repository_arg = quote_arg(&repository.to_string_lossy());
+    let watch_args = iter::once(repository_arg.clone())
+        .chain(args.iter().map(|arg| quote_arg(arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    formatdoc! {"
+        # Generated by `gitwatch service install`, changes are overwritten when reinstalling
+        [Unit]
+        Description=Watch {repository} and automatically commit changes
+        Documentation=https://github.com/croissong/gitwatch-rs
+
+        [Service]
+        Type=notify
+        ExecStart={executable} watch {watch_args}
+        ExecReload={executable} ctl {repository_arg} reload
+        Restart=on-failure
+        RestartSec=10
+        WatchdogSec=60
+
+        [Install]
+        WantedBy=default.target
+        ",
+        repository = repository.display().to_string().replace('%', "%%"),
+    }
+}
+
+// quotes an argument of a unit's command line, see "Command lines" in systemd.service(5)
+fn quote_arg(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,+@".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_path() {
+        let test_cases = [
+            ("/", "-"),
+            ("/home/user/notes", "home-user-notes"),
+            ("/home/user/notes/", "home-user-notes"),
+            ("/home//user/my notes", "home-user-my\\x20notes"),
+            ("/srv/git-repo", "srv-git\\x2drepo"),
+            ("/.notes", "\\x2enotes"),
+            ("/home/user/.notes", "home-user-.notes"),
+        ];
+        for (path, expected) in test_cases {
+            assert_eq!(escape_path(Path::new(path)), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_arg("--debounce-seconds=5"), "--debounce-seconds=5");
+        assert_eq!(quote_arg("Auto commit"), "\"Auto commit\"");
+        assert_eq!(quote_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_arg("100%"), "\"100%%\"");
+        assert_eq!(quote_arg("$HOME"), "\"$$HOME\"");
+        assert_eq!(quote_arg(""), "\"\"");
+    }
+
+    #[test]
+    fn test_render_unit() {
+        let unit = render_unit(
+            Path::new("/usr/bin/gitwatch"),
+            Path::new("/home/user/notes"),
+            &["--commit-message".to_string(), "Auto commit".to_string()],
+        );
+        assert!(unit.contains("Type=notify\n"));
+        assert!(unit.contains(
+            "ExecStart=/usr/bin/gitwatch watch /home/user/notes --commit-message \"Auto commit\"\n"
+        ));
+        assert!(unit.contains("ExecReload=/usr/bin/gitwatch ctl /home/user/notes reload\n"));
+        assert!(unit.contains("WatchdogSec=60\n"));
+    }
+}
@@ -0,0 +1,48 @@
+use std::{process, sync::mpsc::Sender, thread};
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM, SIGUSR1, SIGUSR2},
+    iterator::Signals,
+};
+
+use crate::control::ControlCommand;
+
+/// Translates Unix signals into control commands:
+/// SIGINT/SIGTERM shut down gracefully, SIGUSR1 commits immediately,
+/// SIGUSR2 toggles pause and SIGHUP reloads the configuration.
+pub fn handle_signals(tx: Sender<ControlCommand>) -> Result<()> {
+    let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM, SIGUSR1, SIGUSR2])
+        .context("Failed to register signal handlers")?;
+
+    thread::Builder::new()
+        .name("gitwatch signals".to_string())
+        .spawn(move || {
+            let mut shutting_down = false;
+            for signal in signals.forever() {
+                debug!("Received signal {signal}");
+                let command = match signal {
+                    SIGINT | SIGTERM if shutting_down => {
+                        // e.g. a hanging final push
+                        warn!("Received second termination signal, exiting immediately");
+                        process::exit(128 + signal);
+                    }
+                    SIGINT | SIGTERM => {
+                        shutting_down = true;
+                        ControlCommand::Shutdown
+                    }
+                    SIGUSR1 => ControlCommand::Commit,
+                    SIGUSR2 => ControlCommand::TogglePause,
+                    SIGHUP => ControlCommand::Reload,
+                    _ => continue,
+                };
+                if tx.send(command).is_err() {
+                    // the watch loop has stopped
+                    break;
+                }
+            }
+        })?;
+
+    Ok(())
+}
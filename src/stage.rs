@@ -0,0 +1,76 @@
+use git2::Status;
+use serde::Serialize;
+
+/// Which kinds of working tree changes are staged for autocommits
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StagePolicy {
+    pub deleted: bool,
+    // pause autocommits if a change deletes more files than this
+    pub max_deletions: Option<usize>,
+    // pause autocommits if a change deletes more than this percentage of tracked files
+    pub max_deletion_percent: Option<f64>,
+    pub modified: bool,
+    pub renamed: bool,
+    pub untracked: bool,
+}
+
+impl Default for StagePolicy {
+    fn default() -> Self {
+        Self {
+            deleted: true,
+            max_deletions: None,
+            max_deletion_percent: None,
+            modified: true,
+            renamed: true,
+            untracked: true,
+        }
+    }
+}
+
+impl StagePolicy {
+    /// Returns true if every kind of change is staged
+    pub fn stages_all(&self) -> bool {
+        self.deleted && self.modified && self.renamed && self.untracked
+    }
+
+    /// Returns true if a working tree change with the given status should be staged
+    pub fn allows(&self, status: Status) -> bool {
+        if status.is_wt_renamed() {
+            self.renamed
+        } else if status.is_wt_new() {
+            self.untracked
+        } else if status.is_wt_deleted() {
+            self.deleted
+        } else if status.is_wt_modified() || status.is_wt_typechange() {
+            self.modified
+        } else {
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows() {
+        let policy = StagePolicy::default();
+        assert!(policy.stages_all());
+        assert!(policy.allows(Status::WT_NEW));
+        assert!(policy.allows(Status::WT_DELETED));
+
+        let policy = StagePolicy {
+            deleted: false,
+            untracked: false,
+            ..StagePolicy::default()
+        };
+        assert!(!policy.stages_all());
+        assert!(!policy.allows(Status::WT_NEW));
+        assert!(!policy.allows(Status::WT_DELETED));
+        assert!(policy.allows(Status::WT_MODIFIED));
+        assert!(policy.allows(Status::WT_TYPECHANGE));
+        // renames are controlled independently of the underlying addition & deletion
+        assert!(policy.allows(Status::WT_RENAMED | Status::WT_NEW));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    events::{Event, EventEmitter},
+    fsmonitor::FsMonitor,
+    metrics::Metrics,
+};
+
+/// Runtime state of a gitwatch instance, shared between the watch loop and the control socket
+#[derive(Debug)]
+pub struct AppState {
+    // effective configuration, updated on reload
+    config: Mutex<serde_json::Value>,
+    // owned by the app, so dropping it still waits for the running hooks
+    events: Mutex<Weak<EventEmitter>>,
+    fsmonitor: Arc<FsMonitor>,
+    last_commit: Mutex<Option<SystemTime>>,
+    last_push: Mutex<Option<SystemTime>>,
+    // the safety brake held back a mass deletion, which a resume or requested commit confirms
+    mass_deletion_confirmed: AtomicBool,
+    mass_deletion_paused: AtomicBool,
+    metrics: Metrics,
+    // one-off message used for the next commit instead of the configured one
+    next_commit_message: Mutex<Option<String>>,
+    paused: AtomicBool,
+    repository: PathBuf,
+    started_at: SystemTime,
+}
+
+/// Snapshot of the state of a running gitwatch instance, timestamps are in seconds since the Unix epoch
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Status {
+    pub config: serde_json::Value,
+    pub last_commit: Option<u64>,
+    pub last_push: Option<u64>,
+    pub next_commit_message: Option<String>,
+    pub paused: bool,
+    pub pid: u32,
+    pub repository: PathBuf,
+    pub started_at: u64,
+    pub uptime_seconds: u64,
+}
+
+impl AppState {
+    pub fn new(repository: PathBuf) -> Self {
+        Self {
+            config: Mutex::new(serde_json::Value::Null),
+            events: Mutex::new(Weak::new()),
+            fsmonitor: Arc::default(),
+            last_commit: Mutex::new(None),
+            last_push: Mutex::new(None),
+            mass_deletion_confirmed: AtomicBool::new(false),
+            mass_deletion_paused: AtomicBool::new(false),
+            metrics: Metrics::default(),
+            next_commit_message: Mutex::new(None),
+            paused: AtomicBool::new(false),
+            repository,
+            started_at: SystemTime::now(),
+        }
+    }
+
+    pub fn set_config(&self, config: &AppConfig) -> Result<()> {
+        *self.config.lock().unwrap() = serde_json::to_value(config)?;
+        Ok(())
+    }
+
+    /// Passes manual pauses to the event hooks & observers, updated on reload
+    pub fn set_events(&self, events: &Arc<EventEmitter>) {
+        *self.events.lock().unwrap() = Arc::downgrade(events);
+    }
+
+    pub fn record_commit(&self) {
+        let now = SystemTime::now();
+        *self.last_commit.lock().unwrap() = Some(now);
+        self.metrics.record_commit(unix_timestamp(now));
+    }
+
+    pub fn record_push(&self) {
+        *self.last_push.lock().unwrap() = Some(SystemTime::now());
+        self.metrics.record_push();
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Answers git's fsmonitor queries while the watcher records its events
+    pub fn fsmonitor(&self) -> &Arc<FsMonitor> {
+        &self.fsmonitor
+    }
+
+    pub fn render_metrics(&self) -> String {
+        self.metrics.render(&self.repository, self.is_paused())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Pauses or resumes autocommits, changes are held back while paused
+    pub fn set_paused(&self, paused: bool) {
+        if self.paused.swap(paused, Ordering::SeqCst) == paused {
+            return;
+        }
+        if paused {
+            info!("Pausing autocommits until resumed");
+            let events = self.events.lock().unwrap().upgrade();
+            if let Some(events) = events {
+                events.emit(Event::Pause {
+                    reason: "paused manually".to_string(),
+                });
+            }
+        } else {
+            info!("Resuming autocommits");
+        }
+    }
+
+    /// Marks autocommits as paused by the safety brake, clearing a previous confirmation on resume
+    pub fn set_mass_deletion_paused(&self, paused: bool) {
+        self.mass_deletion_paused.store(paused, Ordering::SeqCst);
+        if !paused {
+            self.mass_deletion_confirmed.store(false, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_mass_deletion_paused(&self) -> bool {
+        self.mass_deletion_paused.load(Ordering::SeqCst)
+    }
+
+    /// Confirms the deletions held back by the safety brake, so they are committed once.
+    /// Returns false if the safety brake isn't engaged.
+    pub fn confirm_mass_deletion(&self) -> bool {
+        if !self.is_mass_deletion_paused() {
+            return false;
+        }
+        if !self.mass_deletion_confirmed.swap(true, Ordering::SeqCst) {
+            info!("Committing the held back deletions as confirmed");
+        }
+        true
+    }
+
+    /// Returns true once after the held back deletions were confirmed
+    pub fn take_mass_deletion_confirmation(&self) -> bool {
+        self.mass_deletion_confirmed.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn next_commit_message(&self) -> Option<String> {
+        self.next_commit_message.lock().unwrap().clone()
+    }
+
+    pub fn set_next_commit_message(&self, message: Option<String>) {
+        *self.next_commit_message.lock().unwrap() = message;
+    }
+
+    pub fn status(&self) -> Status {
+        let started_at = unix_timestamp(self.started_at);
+        Status {
+            config: self.config.lock().unwrap().clone(),
+            last_commit: self.last_commit.lock().unwrap().map(unix_timestamp),
+            last_push: self.last_push.lock().unwrap().map(unix_timestamp),
+            next_commit_message: self.next_commit_message(),
+            paused: self.is_paused(),
+            pid: process::id(),
+            repository: self.repository.clone(),
+            started_at,
+            uptime_seconds: unix_timestamp(SystemTime::now()).saturating_sub(started_at),
+        }
+    }
+}
+
+pub fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+    use crate::observer::GitwatchObserver;
+
+    #[derive(Default)]
+    struct PauseRecorder {
+        reasons: Mutex<Vec<String>>,
+    }
+
+    impl GitwatchObserver for PauseRecorder {
+        fn on_paused(&self, reason: &str) {
+            self.reasons.lock().unwrap().push(reason.to_string());
+        }
+    }
+
+    #[test]
+    fn test_status() -> TestResult {
+        let state = AppState::new(PathBuf::from("/repo"));
+        state.set_config(&AppConfig {
+            commit_message: Some("test".to_string()),
+            ..AppConfig::default()
+        })?;
+
+        let status = state.status();
+        assert_eq!(status.pid, process::id());
+        assert_eq!(status.last_commit, None);
+        assert_eq!(status.config["commit_message"], "test");
+        assert_eq!(status.config["commit_mode"], "debounce");
+
+        state.record_commit();
+        let status = state.status();
+        assert!(status.last_commit.is_some_and(|time| time >= status.started_at));
+        assert_eq!(status.last_push, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_manual_pause_event() {
+        let state = AppState::new(PathBuf::from("/repo"));
+        let observer = Arc::new(PauseRecorder::default());
+        let events = Arc::new(EventEmitter::default().with_observers(vec![observer.clone()]));
+        state.set_events(&events);
+
+        state.set_paused(true);
+        // already paused
+        state.set_paused(true);
+        state.set_paused(false);
+        assert_eq!(*observer.reasons.lock().unwrap(), vec!["paused manually"]);
+
+        // the app owns the emitter, pausing after it stopped only updates the state
+        drop(events);
+        state.set_paused(true);
+        assert!(state.is_paused());
+        assert_eq!(observer.reasons.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, Result};
+use git2::{Repository, Status as FileStatus, StatusOptions};
+use serde::Serialize;
+
+use crate::{
+    control::query_status,
+    repo::get_current_branch,
+    state::{unix_timestamp, Status},
+};
+
+/// Report printed by `gitwatch status`
+#[derive(Debug, Serialize)]
+pub struct StatusReport {
+    // state of the running gitwatch instance, if any
+    pub instance: Option<Status>,
+    #[serde(flatten)]
+    pub repo_status: RepoStatus,
+    pub repository: PathBuf,
+    pub running: bool,
+}
+
+/// State of the repository itself, available even if gitwatch isn't running
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct RepoStatus {
+    // number of commits not pushed yet, None if the branch has no upstream
+    pub ahead: Option<usize>,
+    // None if HEAD is detached
+    pub branch: Option<String>,
+    // number of uncommitted changes in the working tree & index
+    pub pending_changes: usize,
+}
+
+impl StatusReport {
+    pub fn new(repository: &Path) -> Result<Self> {
+        let instance = query_status(repository)?;
+        // a running instance pushes to its configured remote instead of the branch's upstream
+        let remote = instance
+            .as_ref()
+            .and_then(|status| status.config["remote"].as_str().map(String::from));
+        let repo_status = RepoStatus::new(repository, remote.as_deref())?;
+
+        Ok(Self {
+            running: instance.is_some(),
+            instance,
+            repo_status,
+            repository: repository.to_path_buf(),
+        })
+    }
+}
+
+impl RepoStatus {
+    pub fn new(repository: &Path, remote: Option<&str>) -> Result<Self> {
+        let repo = Repository::open(repository).with_context(|| {
+            format!("Failed to open git repository {}", repository.display())
+        })?;
+        let branch = get_current_branch(&repo)?;
+        let ahead = match &branch {
+            Some(branch) => get_ahead_count(&repo, branch, remote)?,
+            None => None,
+        };
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true);
+        options.recurse_untracked_dirs(true);
+        let pending_changes = repo
+            .statuses(Some(&mut options))?
+            .iter()
+            .filter(|entry| !entry.status().intersects(FileStatus::IGNORED))
+            .count();
+
+        Ok(Self {
+            ahead,
+            branch,
+            pending_changes,
+        })
+    }
+}
+
+// Returns the number of commits on `branch` that are not on its upstream, if it has one
+fn get_ahead_count(repo: &Repository, branch: &str, remote: Option<&str>) -> Result<Option<usize>> {
+    // unborn branch
+    let Ok(local_oid) = repo.refname_to_id("HEAD") else {
+        return Ok(None);
+    };
+    let upstream_name = match remote {
+        Some(remote) => format!("refs/remotes/{remote}/{branch}"),
+        None => match repo.branch_upstream_name(&format!("refs/heads/{branch}")) {
+            Ok(name) => name.as_str().unwrap_or_default().to_string(),
+            Err(_) => return Ok(None),
+        },
+    };
+    let Ok(upstream_oid) = repo.refname_to_id(&upstream_name) else {
+        return Ok(None);
+    };
+    let (ahead, _behind) = repo.graph_ahead_behind(local_oid, upstream_oid)?;
+    Ok(Some(ahead))
+}
+
+impl Display for StatusReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.instance {
+            Some(instance) => writeln!(
+                f,
+                "gitwatch is running (PID {}, up {})",
+                instance.pid,
+                format_duration(instance.uptime_seconds)
+            )?,
+            None => writeln!(f, "gitwatch is not running")?,
+        }
+        writeln!(f, "Repository:       {}", self.repository.display())?;
+        writeln!(
+            f,
+            "Branch:           {}",
+            self.repo_status.branch.as_deref().unwrap_or("(detached HEAD)")
+        )?;
+        if let Some(instance) = &self.instance {
+            let autocommits = if instance.paused { "paused" } else { "active" };
+            writeln!(f, "Autocommits:      {autocommits}")?;
+            writeln!(f, "Last commit:      {}", format_time(instance.last_commit))?;
+            writeln!(f, "Last push:        {}", format_time(instance.last_push))?;
+            if let Some(message) = &instance.next_commit_message {
+                writeln!(f, "Next message:     {message}")?;
+            }
+        }
+        match self.repo_status.ahead {
+            Some(ahead) => writeln!(f, "Unpushed commits: {ahead}")?,
+            None => writeln!(f, "Unpushed commits: unknown (no upstream)")?,
+        }
+        write!(f, "Pending changes:  {}", self.repo_status.pending_changes)?;
+
+        if let Some(instance) = &self.instance {
+            write!(f, "\n\nEffective config:")?;
+            write_config(f, &instance.config, 1)?;
+        }
+        Ok(())
+    }
+}
+
+// writes the config as indented `key: value` lines
+fn write_config(f: &mut Formatter<'_>, value: &serde_json::Value, depth: usize) -> fmt::Result {
+    let Some(object) = value.as_object() else {
+        return Ok(());
+    };
+    let indent = "  ".repeat(depth);
+    for (key, value) in object {
+        match value {
+            serde_json::Value::Object(_) => {
+                write!(f, "\n{indent}{key}:")?;
+                write_config(f, value, depth + 1)?;
+            }
+            _ => write!(f, "\n{indent}{key}: {}", format_value(value))?,
+        }
+    }
+    Ok(())
+}
+
+fn format_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => "-".to_string(),
+        serde_json::Value::String(string) => string.clone(),
+        serde_json::Value::Array(values) if values.is_empty() => "-".to_string(),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(format_value)
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.to_string(),
+    }
+}
+
+fn format_time(timestamp: Option<u64>) -> String {
+    match timestamp {
+        Some(timestamp) => {
+            let elapsed = unix_timestamp(SystemTime::now()).saturating_sub(timestamp);
+            format!("{} ago", format_duration(elapsed))
+        }
+        None => "never".to_string(),
+    }
+}
+
+// formats a duration with its two most significant units, e.g. "1h 5m"
+fn format_duration(seconds: u64) -> String {
+    let units = [("d", 24 * 60 * 60), ("h", 60 * 60), ("m", 60), ("s", 1)];
+    let parts = units
+        .iter()
+        .scan(seconds, |remaining, (unit, unit_seconds)| {
+            let value = *remaining / unit_seconds;
+            *remaining %= unit_seconds;
+            Some((value, unit))
+        })
+        .skip_while(|(value, _)| *value == 0)
+        .take(2)
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use testresult::TestResult;
+
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(60), "1m");
+        assert_eq!(format_duration(3 * 60 * 60 + 5 * 60 + 7), "3h 5m");
+        assert_eq!(format_duration(2 * 24 * 60 * 60 + 30), "2d");
+    }
+
+    #[test]
+    fn test_repo_status() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let repo = Repository::init(temp_dir.path())?;
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Test User")?;
+        config.set_str("user.email", "test@example.com")?;
+
+        fs::write(temp_dir.path().join("foo.txt"), "foo")?;
+        let status = RepoStatus::new(temp_dir.path(), Some("origin"))?;
+        assert_eq!(status.ahead, None);
+        assert_eq!(status.pending_changes, 1);
+
+        let signature = repo.signature()?;
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let first = repo.commit(Some("HEAD"), &signature, &signature, "first", &tree, &[])?;
+        let branch = get_current_branch(&repo)?.unwrap();
+        repo.reference(&format!("refs/remotes/origin/{branch}"), first, true, "")?;
+        let parent = repo.find_commit(first)?;
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "second",
+            &tree,
+            &[&parent],
+        )?;
+
+        let status = RepoStatus::new(temp_dir.path(), Some("origin"))?;
+        assert_eq!(
+            status,
+            RepoStatus {
+                ahead: Some(1),
+                branch: Some(branch),
+                pending_changes: 1,
+            }
+        );
+        // no upstream configured for the branch
+        assert_eq!(RepoStatus::new(temp_dir.path(), None)?.ahead, None);
+        Ok(())
+    }
+}
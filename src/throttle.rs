@@ -0,0 +1,114 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+/// Why a commit is currently not allowed, with the remaining time until it is
+#[derive(Debug, PartialEq)]
+pub enum Throttled {
+    MinCommitInterval(Duration),
+    MaxCommitsPerHour(Duration),
+}
+
+impl Throttled {
+    pub fn remaining(&self) -> Duration {
+        match self {
+            Self::MinCommitInterval(remaining) | Self::MaxCommitsPerHour(remaining) => *remaining,
+        }
+    }
+}
+
+/// Limits how often changes are committed
+pub struct CommitThrottle {
+    commit_times: VecDeque<Instant>,
+    max_commits_per_hour: Option<u32>,
+    min_commit_interval: Duration,
+}
+
+impl CommitThrottle {
+    pub fn new(min_commit_interval: Duration, max_commits_per_hour: Option<u32>) -> Self {
+        Self {
+            commit_times: VecDeque::new(),
+            max_commits_per_hour,
+            min_commit_interval,
+        }
+    }
+
+    /// Returns why committing at `now` is not allowed, if it isn't
+    pub fn check(&mut self, now: Instant) -> Option<Throttled> {
+        // forget commits older than an hour
+        while self
+            .commit_times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= HOUR)
+        {
+            self.commit_times.pop_front();
+        }
+
+        if let Some(max_commits) = self.max_commits_per_hour {
+            if self.commit_times.len() >= max_commits as usize {
+                let oldest = self.commit_times.front().copied().unwrap_or(now);
+                return Some(Throttled::MaxCommitsPerHour(
+                    (oldest + HOUR).saturating_duration_since(now),
+                ));
+            }
+        }
+
+        match self.commit_times.back() {
+            Some(last) if now.duration_since(*last) < self.min_commit_interval => Some(
+                Throttled::MinCommitInterval((*last + self.min_commit_interval) - now),
+            ),
+            _ => None,
+        }
+    }
+
+    pub fn record_commit(&mut self, now: Instant) {
+        self.commit_times.push_back(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited() {
+        let mut throttle = CommitThrottle::new(Duration::ZERO, None);
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert_eq!(throttle.check(now), None);
+            throttle.record_commit(now);
+        }
+    }
+
+    #[test]
+    fn test_min_commit_interval() {
+        let mut throttle = CommitThrottle::new(Duration::from_secs(60), None);
+        let start = Instant::now();
+        assert_eq!(throttle.check(start), None);
+        throttle.record_commit(start);
+
+        assert_eq!(
+            throttle.check(start + Duration::from_secs(20)),
+            Some(Throttled::MinCommitInterval(Duration::from_secs(40)))
+        );
+        assert_eq!(throttle.check(start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn test_max_commits_per_hour() {
+        let mut throttle = CommitThrottle::new(Duration::ZERO, Some(2));
+        let start = Instant::now();
+        throttle.record_commit(start);
+        throttle.record_commit(start + Duration::from_secs(10 * 60));
+
+        assert_eq!(
+            throttle.check(start + Duration::from_secs(20 * 60)),
+            Some(Throttled::MaxCommitsPerHour(Duration::from_secs(40 * 60)))
+        );
+        // the first commit is older than an hour
+        assert_eq!(throttle.check(start + HOUR), None);
+    }
+}
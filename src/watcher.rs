@@ -1,126 +1,456 @@
 use std::{
+    collections::BTreeSet,
     path::{Path, PathBuf},
-    sync::mpsc::{self, Receiver},
+    sync::{mpsc::Sender, Arc},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::{Context, Result};
+use clap::ValueEnum;
+use git2::Oid;
 use log::{debug, error, info, trace, warn};
-use notify_debouncer_full::{
-    new_debouncer,
-    notify::{EventKind, RecursiveMode},
-    DebouncedEvent,
+use notify::{EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    control::{ControlCommand, StopReason},
+    event_loop::{Timer, Timers, WatchChannel, WatchEvent},
+    events::{Event, EventEmitter},
+    fsmonitor::FsMonitor,
+    log_file::LogFile,
+    sd_notify,
+    state::AppState,
+    throttle::{CommitThrottle, Throttled},
 };
 
+/// When changes are committed
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommitMode {
+    // commit once no filesystem events arrived for the debounce time
+    #[default]
+    Debounce,
+    // commit all changes every commit interval, without watching for events
+    Interval,
+    // commit after the debounce time, but at least every commit interval
+    Combined,
+}
+
+/// How filesystem changes are detected
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatcherBackend {
+    // the platform's native notification API (e.g. inotify)
+    #[default]
+    Native,
+    // periodically scan the repository and compare file contents
+    Poll,
+}
+
 pub struct FileWatcher {
-    debounce_seconds: u64,
+    backend: WatcherBackend,
+    commit_interval: Duration,
+    commit_mode: CommitMode,
+    debounce: Duration,
+    events: Arc<EventEmitter>,
+    // written while the events are logged, see `collect_paths`
+    log_file: Option<LogFile>,
+    max_commits_per_hour: Option<u32>,
+    min_commit_interval: Duration,
+    poll_interval: Duration,
+    reconcile_interval: Duration,
     retry_count: i32,
+    state: Arc<AppState>,
 }
 
 impl FileWatcher {
-    pub fn new(debounce_seconds: u64, retry_count: i32) -> Self {
+    pub fn new(config: &AppConfig) -> Self {
         Self {
-            debounce_seconds,
-            retry_count,
+            backend: config.watcher_backend,
+            commit_interval: Duration::from_secs(config.commit_interval_seconds),
+            commit_mode: config.commit_mode,
+            debounce: Duration::from_secs(config.debounce_seconds),
+            events: Arc::new(EventEmitter::new(config)),
+            log_file: config.log_file.clone(),
+            max_commits_per_hour: config.max_commits_per_hour,
+            min_commit_interval: Duration::from_secs(config.min_commit_interval_seconds),
+            poll_interval: Duration::from_secs(config.poll_interval_seconds),
+            reconcile_interval: Duration::from_secs(config.reconcile_interval_seconds),
+            retry_count: config.retries,
+            state: Arc::new(AppState::new(config.repository.clone())),
         }
     }
 
+    /// Shares the pause state with other components, e.g. the control socket
+    pub fn with_state(mut self, state: Arc<AppState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Shares the event hooks with other components, e.g. the repository
+    pub fn with_events(mut self, events: Arc<EventEmitter>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Watches `path` and calls `on_change` for detected changes until
+    /// a shutdown or reload is requested via `channel`.
+    /// Remaining changes are committed before returning.
     pub fn watch<F, P>(
         &self,
         path: &Path,
         on_change: F,
         is_path_ignored: P,
-        shutdown_rx: Option<Receiver<()>>,
-    ) -> Result<()>
+        channel: &WatchChannel,
+    ) -> Result<StopReason>
     where
-        F: Fn(&Vec<PathBuf>) -> Result<()>,
+        F: Fn(&Vec<PathBuf>) -> Result<Option<Oid>>,
         P: Fn(&Path) -> bool,
     {
-        let (tx, rx) = mpsc::channel();
-
-        let mut debouncer = new_debouncer(Duration::from_secs(self.debounce_seconds), None, tx)?;
+        // interval mode snapshots the working tree without listening for events
+        let watcher = if self.commit_mode == CommitMode::Interval {
+            info!(
+                "Committing changes every {}s...",
+                self.commit_interval.as_secs()
+            );
+            None
+        } else {
+            let watcher = self.create_watcher(path, channel.sender())?;
+            info!("Watching for changes...");
+            Some(watcher)
+        };
+        sd_notify::ready();
+
+        // the loop sleeps until an event arrives or the next timer is due, so while idle it only
+        // wakes up for the enabled timers (and the poll backend scans every poll interval)
+        let mut timers = Timers::default();
+        let watchdog_interval = sd_notify::watchdog_interval();
+        if watchdog_interval.is_some() {
+            timers.set(Timer::Watchdog, Instant::now());
+        }
+        if self.commit_mode != CommitMode::Debounce {
+            timers.set(Timer::IntervalCommit, Instant::now() + self.commit_interval);
+        }
+        // interval commits always stage the whole working tree
+        if self.commit_mode != CommitMode::Interval && !self.reconcile_interval.is_zero() {
+            timers.set(Timer::Reconcile, Instant::now() + self.reconcile_interval);
+        }
 
-        debouncer
-            .watch(path, RecursiveMode::Recursive)
-            .context("Failed to watch path")?;
-        info!("Watching for changes...");
+        let mut throttle = CommitThrottle::new(self.min_commit_interval, self.max_commits_per_hour);
+        // changed paths waiting for the debounce time to pass without further events
+        let mut debounced_paths = BTreeSet::new();
+        // changes waiting to be committed, held back while commits are paused or throttled
+        let mut pending_paths: Option<BTreeSet<PathBuf>> = None;
+        let mut is_throttled = false;
+        // a requested commit bypasses pause & rate limits
+        let mut force_commit = false;
+
+        let stop_reason = loop {
+            for timer in timers.take_due(Instant::now()) {
+                match timer {
+                    Timer::Debounce => {
+                        let pending_paths = pending_paths.get_or_insert_with(BTreeSet::new);
+                        pending_paths.append(&mut debounced_paths);
+                        self.state
+                            .metrics()
+                            .set_pending_changes(pending_paths.len());
+                    }
+                    Timer::IntervalCommit => {
+                        debug!("Commit interval elapsed");
+                        // interval commits are not triggered by specific paths
+                        pending_paths.get_or_insert_with(BTreeSet::new);
+                        timers.set(Timer::IntervalCommit, Instant::now() + self.commit_interval);
+                    }
+                    // the held back changes are committed below
+                    Timer::Throttle => {}
+                    // held back changes are staged with a full scan once they are committed,
+                    // while rate limited the reconcile waits for the next interval
+                    Timer::Reconcile => {
+                        if pending_paths.is_none()
+                            && !self.state.is_paused()
+                            && throttle.check(Instant::now()).is_none()
+                        {
+                            debug!("Reconciling the whole working tree");
+                            match self.run_with_retries(&Vec::new(), &on_change) {
+                                Ok(Some(_)) => throttle.record_commit(Instant::now()),
+                                Ok(None) => {}
+                                Err(e) => {
+                                    error!(event = "error"; "All retry attempts failed: {e}");
+                                    self.emit_error(&e);
+                                    return Err(e);
+                                }
+                            }
+                        }
+                        timers.set(Timer::Reconcile, Instant::now() + self.reconcile_interval);
+                    }
+                    Timer::Watchdog => {
+                        sd_notify::watchdog();
+                        if let Some(interval) = watchdog_interval {
+                            timers.set(Timer::Watchdog, Instant::now() + interval);
+                        }
+                    }
+                }
+            }
 
-        loop {
-            if let Some(rx) = &shutdown_rx {
-                if rx.try_recv().is_ok() {
-                    debug!("Received shutdown signal");
-                    break;
+            if pending_paths.is_some() && (force_commit || !self.state.is_paused()) {
+                let throttled = if force_commit {
+                    None
+                } else {
+                    throttle.check(Instant::now())
+                };
+                match throttled {
+                    None => {
+                        let paths = pending_paths.take().unwrap_or_default();
+                        match self.run_with_retries(&paths.into_iter().collect(), &on_change) {
+                            // only created commits count towards the rate limits
+                            Ok(Some(_)) => throttle.record_commit(Instant::now()),
+                            Ok(None) => {}
+                            Err(e) => {
+                                error!(event = "error"; "All retry attempts failed: {e}");
+                                self.emit_error(&e);
+                                return Err(e);
+                            }
+                        }
+                        self.state.metrics().set_pending_changes(0);
+                        timers.cancel(Timer::Throttle);
+                        is_throttled = false;
+                        force_commit = false;
+                        // a debounced commit restarts the commit interval
+                        if timers.is_set(Timer::IntervalCommit) {
+                            timers
+                                .set(Timer::IntervalCommit, Instant::now() + self.commit_interval);
+                        }
+                    }
+                    Some(throttled) => {
+                        if !is_throttled {
+                            self.log_throttled(&throttled);
+                            is_throttled = true;
+                        }
+                        timers.set(Timer::Throttle, Instant::now() + throttled.remaining());
+                    }
                 }
             }
 
-            match rx.recv_timeout(Duration::from_millis(100)) {
-                Ok(received) => match received {
-                    Ok(events) => {
-                        if let Err(e) = self.handle_events(events, &on_change, &is_path_ignored) {
-                            error!("All retry attempts failed: {e}");
-                            return Err(e);
+            match channel.recv(timers.next_deadline()) {
+                // a timer is due
+                None => {}
+                Some(WatchEvent::Control(command)) => match command {
+                    ControlCommand::Shutdown => {
+                        debug!("Received shutdown signal");
+                        break StopReason::Shutdown;
+                    }
+                    ControlCommand::Reload => {
+                        debug!("Received reload signal");
+                        break StopReason::Reload;
+                    }
+                    ControlCommand::Commit => {
+                        info!("Committing changes on request");
+                        self.state.confirm_mass_deletion();
+                        // without waiting for the debounce time
+                        pending_paths
+                            .get_or_insert_with(BTreeSet::new)
+                            .append(&mut debounced_paths);
+                        timers.cancel(Timer::Debounce);
+                        force_commit = true;
+                    }
+                    // resuming from the safety brake confirms the held back deletions
+                    ControlCommand::TogglePause => {
+                        if self.state.is_paused() {
+                            self.state.set_paused(false);
+                            self.state.confirm_mass_deletion();
+                        } else if self.state.confirm_mass_deletion() {
+                            pending_paths.get_or_insert_with(BTreeSet::new);
+                        } else {
+                            self.state.set_paused(true);
+                        }
+                    }
+                    // held back changes are committed on the next iteration,
+                    // confirmed deletions as well although they are no pending paths anymore
+                    ControlCommand::Wake => {
+                        if self.state.is_mass_deletion_paused() {
+                            pending_paths.get_or_insert_with(BTreeSet::new);
                         }
                     }
-                    Err(errors) => errors.iter().for_each(|error| error!("{error:?}")),
                 },
-                Err(mpsc::RecvTimeoutError::Timeout) => continue,
-                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                // every event restarts the debounce time, without a thread ticking in the background
+                Some(WatchEvent::Changes(Ok(event))) => {
+                    let paths = self.collect_paths(event, &is_path_ignored);
+                    if !paths.is_empty() {
+                        // the same path is usually reported by multiple events
+                        debounced_paths.extend(paths);
+                        timers.set(Timer::Debounce, Instant::now() + self.debounce);
+                    }
+                }
+                Some(WatchEvent::Changes(Err(error))) => {
+                    self.state.metrics().record_watcher_error();
+                    error!("{error:?}");
+                }
             }
+        };
+
+        if let Some(watcher) = watcher {
+            self.state.fsmonitor().stop();
+            drop(watcher);
+        }
+        if stop_reason == StopReason::Shutdown {
+            sd_notify::stopping();
         }
 
-        Ok(())
+        // flush changes that were detected, but not committed yet,
+        // without pending changes the whole working tree is staged, including the debounced ones
+        let paths = pending_paths
+            .map(|mut paths| {
+                paths.append(&mut debounced_paths);
+                paths
+            })
+            .unwrap_or_default();
+        if self.state.is_paused() {
+            info!("Autocommits are paused, skipping final commit");
+        } else if let Err(e) = self.run_with_retries(&paths.into_iter().collect(), &on_change) {
+            error!(event = "error"; "All retry attempts failed: {e}");
+            self.emit_error(&e);
+            return Err(e);
+        } else {
+            self.state.metrics().set_pending_changes(0);
+        }
+        Ok(stop_reason)
     }
 
-    fn handle_events<F, P>(
-        &self,
-        events: Vec<DebouncedEvent>,
-        on_change: F,
-        is_path_ignored: P,
-    ) -> Result<()>
+    // dropping the watcher stops watching
+    fn create_watcher(&self, path: &Path, sender: Sender<WatchEvent>) -> Result<Box<dyn Watcher>> {
+        if self.backend == WatcherBackend::Native {
+            let mut watcher = RecommendedWatcher::new(
+                record_changes(self.state.fsmonitor().clone(), sender.clone()),
+                notify::Config::default(),
+            )?;
+            match watcher.watch(path, RecursiveMode::Recursive) {
+                Ok(()) => {
+                    self.state.fsmonitor().start(path);
+                    return Ok(Box::new(watcher));
+                }
+                Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => {
+                    warn!(
+                        "Native file watch limit reached (ENOSPC), falling back to polling every {:?}. \
+                        Consider raising fs.inotify.max_user_watches.",
+                        self.poll_interval
+                    );
+                }
+                Err(e) => return Err(e).context("Failed to watch path"),
+            }
+        }
+
+        debug!("Polling for changes every {:?}", self.poll_interval);
+        let config = notify::Config::default()
+            .with_poll_interval(self.poll_interval)
+            .with_compare_contents(true);
+        let mut watcher = PollWatcher::new(forward_changes(sender), config)?;
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .context("Failed to watch path")?;
+        Ok(Box::new(watcher))
+    }
+
+    fn log_throttled(&self, throttled: &Throttled) {
+        match throttled {
+            Throttled::MinCommitInterval(remaining) => info!(
+                "Holding changes for {}s until the minimum commit interval has passed",
+                remaining.as_secs()
+            ),
+            Throttled::MaxCommitsPerHour(remaining) => warn!(
+                "Reached the maximum of {} commits per hour, holding changes for {}s",
+                self.max_commits_per_hour.unwrap_or_default(),
+                remaining.as_secs()
+            ),
+        }
+    }
+
+    // returns the paths of the event if it is relevant, without the ignored paths
+    fn collect_paths<P>(&self, event: notify::Event, is_path_ignored: P) -> Vec<PathBuf>
     where
-        F: Fn(&Vec<PathBuf>) -> Result<()>,
         P: Fn(&Path) -> bool,
     {
-        trace!("Received notify events {{ events {events:?} }}");
-        let paths: Vec<_> = events
-            .iter()
-            .filter(|event| !matches!(event.kind, EventKind::Access(_) | EventKind::Other))
-            .flat_map(|event| event.paths.clone())
-            .filter(|path| !is_path_ignored(path))
-            .collect();
-
-        if !paths.is_empty() {
-            let mut retry_count = 0;
-            loop {
-                match on_change(&paths) {
-                    Ok(()) => break,
-                    Err(e) => {
-                        if retry_count == self.retry_count {
-                            return Err(e);
-                        }
-                        retry_count += 1;
-                        warn!(
-                            "Failed to commit changes. Retrying... ({}/{}).\nError: {:?}",
-                            retry_count, self.retry_count, e
-                        );
-                        thread::sleep(RETRY_DELAY);
+        // logging the log file's own events would write it again, waking up the loop endlessly
+        if self.is_log_file_event(&event) {
+            return Vec::new();
+        }
+
+        trace!("Received notify event {event:?}");
+        if matches!(event.kind, EventKind::Access(_) | EventKind::Other) {
+            return Vec::new();
+        }
+        let (ignored, paths): (Vec<_>, Vec<_>) = event
+            .paths
+            .into_iter()
+            .partition(|path| is_path_ignored(path));
+        self.state.metrics().record_ignored_events(ignored.len());
+        paths
+    }
+
+    fn is_log_file_event(&self, event: &notify::Event) -> bool {
+        self.log_file.as_ref().is_some_and(|log_file| {
+            !event.paths.is_empty() && event.paths.iter().all(|path| log_file.is_log_path(path))
+        })
+    }
+
+    fn emit_error(&self, error: &anyhow::Error) {
+        self.events.emit(Event::Error {
+            error: format!("{error:#}"),
+        });
+    }
+
+    fn run_with_retries<F>(&self, paths: &Vec<PathBuf>, on_change: F) -> Result<Option<Oid>>
+    where
+        F: Fn(&Vec<PathBuf>) -> Result<Option<Oid>>,
+    {
+        let mut retry_count = 0;
+        loop {
+            match on_change(paths) {
+                Ok(commit_id) => return Ok(commit_id),
+                Err(e) => {
+                    if retry_count == self.retry_count {
+                        return Err(e);
                     }
+                    retry_count += 1;
+                    warn!(
+                        "Failed to commit changes. Retrying... ({}/{}).\nError: {:?}",
+                        retry_count, self.retry_count, e
+                    );
+                    thread::sleep(RETRY_DELAY);
                 }
             }
         }
-        Ok(())
     }
 }
 
 const RETRY_DELAY: Duration = Duration::from_secs(1);
 
+// sends the raw events to the watch loop, which may already have stopped
+fn forward_changes(sender: Sender<WatchEvent>) -> impl Fn(notify::Result<notify::Event>) + Send {
+    move |result| {
+        let _ = sender.send(WatchEvent::Changes(result));
+    }
+}
+
+// also records the raw events for git's fsmonitor queries, which don't wait for the debounce time
+fn record_changes(
+    fsmonitor: Arc<FsMonitor>,
+    sender: Sender<WatchEvent>,
+) -> impl Fn(notify::Result<notify::Event>) + Send {
+    let forward = forward_changes(sender);
+    move |result| {
+        fsmonitor.record(&result);
+        forward(result);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
         fs,
         sync::{
-            atomic::{AtomicBool, AtomicU32, Ordering},
+            atomic::{AtomicU32, Ordering},
             mpsc, Arc,
         },
         thread,
@@ -128,23 +458,28 @@ mod tests {
     };
 
     use anyhow::bail;
-    use notify_debouncer_full::notify::Event;
+    use notify::Event;
     use testresult::TestResult;
 
     use super::*;
+    use crate::{cli::LogLevel, log_file::LogRotation};
 
     #[test]
     fn test_watcher_notify_error() -> TestResult {
         let dir = tempfile::tempdir()?;
         let path = dir.path().to_path_buf();
 
-        let watcher = FileWatcher::new(0, 2);
+        let watcher = FileWatcher::new(&AppConfig {
+            debounce_seconds: 0,
+            retries: 2,
+            ..AppConfig::default()
+        });
 
         // delete the directory being watched
         fs::remove_dir_all(&path)?;
 
         // try to watch - should get notify error
-        let result = watcher.watch(&path, |_| Ok(()), |_path| false, None);
+        let result = watcher.watch(&path, |_| Ok(None), |_path| false, &WatchChannel::new());
         assert!(result.is_err());
         let err = result.unwrap_err().to_string();
 
@@ -165,8 +500,14 @@ mod tests {
         let attempt_count = Arc::new(AtomicU32::new(0));
         let attempt_count_clone = attempt_count.clone();
 
-        let watcher = FileWatcher::new(0, 2);
+        let watcher = FileWatcher::new(&AppConfig {
+            debounce_seconds: 0,
+            retries: 2,
+            ..AppConfig::default()
+        });
         let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
 
         // start watching in a separate thread
         let temp_dir_path = temp_dir.path().to_owned();
@@ -178,7 +519,7 @@ mod tests {
                     bail!("Mock callback error")
                 },
                 |_path| false,
-                Some(shutdown_rx),
+                &channel,
             )
         });
 
@@ -190,7 +531,7 @@ mod tests {
         // Wait for all retries (2 retries * 1 second sleep between retries)
         thread::sleep(Duration::from_secs(2));
 
-        let _ = shutdown_tx.send(());
+        let _ = shutdown_tx.send(ControlCommand::Shutdown);
 
         match handle.join().expect("Thread panicked") {
             Ok(_) => panic!("Expected an error from watcher"),
@@ -217,8 +558,14 @@ mod tests {
 
         // create channels for shutdown signal
         let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
 
-        let watcher = FileWatcher::new(1, 0);
+        let watcher = FileWatcher::new(&AppConfig {
+            debounce_seconds: 1,
+            retries: 0,
+            ..AppConfig::default()
+        });
 
         // Create a counter to track number of changes
         let counter = Arc::new(AtomicU32::new(0));
@@ -230,10 +577,10 @@ mod tests {
                 &temp_dir_path,
                 |_| {
                     counter_clone.fetch_add(1, Ordering::SeqCst);
-                    Ok(())
+                    Ok(None)
                 },
                 |_| false,
-                Some(shutdown_rx),
+                &channel,
             )
         });
 
@@ -249,50 +596,376 @@ mod tests {
         let counter_before_shutdown = counter.load(Ordering::SeqCst);
         assert!(counter_before_shutdown > 0);
 
-        shutdown_tx.send(())?;
-        handle.join().unwrap()?;
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        assert_eq!(handle.join().unwrap()?, StopReason::Shutdown);
+
+        // remaining changes are flushed once on shutdown
+        let counter_after_shutdown = counter.load(Ordering::SeqCst);
+        assert_eq!(counter_before_shutdown + 1, counter_after_shutdown);
 
         // create another file after shutdown
         fs::write(test_file_2, "test content")?;
         thread::sleep(Duration::from_secs(2));
 
         // verify no additional changes were detected
-        assert_eq!(counter_before_shutdown, counter.load(Ordering::SeqCst));
+        assert_eq!(counter_after_shutdown, counter.load(Ordering::SeqCst));
 
         Ok(())
     }
 
+    #[test]
+    fn test_debounce_restarted_by_events() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            debounce_seconds: 1,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send((Instant::now(), paths.clone()))?;
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+        thread::sleep(Duration::from_millis(100));
+
+        // every change arrives before the debounce time of the previous one has passed
+        let mut last_change = Instant::now();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            thread::sleep(Duration::from_millis(600));
+            fs::write(temp_dir.path().join(name), name)?;
+            last_change = Instant::now();
+        }
+
+        let (committed, paths) = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(committed - last_change >= Duration::from_secs(1));
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(paths.contains(&temp_dir.path().join(name)));
+        }
+
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_poll_backend() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file = temp_dir.path().join("test.txt");
+        fs::write(&test_file, "initial content")?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            poll_interval_seconds: 1,
+            watcher_backend: WatcherBackend::Poll,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send(paths.clone())?;
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+
+        // give the poll watcher time for its initial scan
+        thread::sleep(Duration::from_millis(300));
+
+        // same size, different content: only detected via content comparison
+        fs::write(&test_file, "changed content")?;
+
+        let paths = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(paths.contains(&test_file), "Unexpected paths: {paths:?}");
+
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_mode() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            commit_interval_seconds: 1,
+            commit_mode: CommitMode::Interval,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send(paths.clone())?;
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+
+        // no filesystem events needed, on_change is called for every interval
+        let start = Instant::now();
+        changes_rx.recv_timeout(Duration::from_secs(5))?;
+        changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(start.elapsed() >= Duration::from_secs(2));
+
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_combined_mode() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file = temp_dir.path().join("test.txt");
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            commit_interval_seconds: 1,
+            commit_mode: CommitMode::Combined,
+            debounce_seconds: 10,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send(paths.clone())?;
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+
+        // write continuously, so the debounce time never elapses
+        let writer = thread::spawn(move || -> Result<()> {
+            for i in 0..30 {
+                fs::write(&test_file, format!("content {i}"))?;
+                thread::sleep(Duration::from_millis(100));
+            }
+            Ok(())
+        });
+
+        // the commit interval forces a commit nonetheless
+        let paths = changes_rx.recv_timeout(Duration::from_secs(2))?;
+        assert!(paths.is_empty(), "Unexpected paths: {paths:?}");
+
+        writer.join().unwrap()?;
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_commit_interval() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            min_commit_interval_seconds: 2,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send((Instant::now(), paths.clone()))?;
+                    Ok(Some(Oid::ZERO_SHA1))
+                },
+                |_| false,
+                &channel,
+            )
+        });
+        thread::sleep(Duration::from_millis(100));
+
+        fs::write(temp_dir.path().join("a.txt"), "a")?;
+        let (first_commit, _) = changes_rx.recv_timeout(Duration::from_secs(5))?;
+
+        // both changes are held back and committed together
+        fs::write(temp_dir.path().join("b.txt"), "b")?;
+        thread::sleep(Duration::from_millis(300));
+        fs::write(temp_dir.path().join("c.txt"), "c")?;
+
+        let (second_commit, paths) = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(second_commit - first_commit >= Duration::from_secs(2));
+        assert!(paths.contains(&temp_dir.path().join("b.txt")));
+        assert!(paths.contains(&temp_dir.path().join("c.txt")));
+
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_commit_interval_without_commit() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (shutdown_tx, shutdown_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(shutdown_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig {
+            // batches the events of each write
+            debounce_seconds: 1,
+            min_commit_interval_seconds: 60,
+            ..AppConfig::default()
+        });
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send(paths.clone())?;
+                    // nothing to commit, e.g. the change was reverted in the meantime
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+        thread::sleep(Duration::from_millis(100));
+
+        fs::write(temp_dir.path().join("a.txt"), "a")?;
+        changes_rx.recv_timeout(Duration::from_secs(5))?;
+
+        // without a created commit, the next change is not held back
+        fs::write(temp_dir.path().join("b.txt"), "b")?;
+        let paths = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(paths.contains(&temp_dir.path().join("b.txt")));
+
+        shutdown_tx.send(ControlCommand::Shutdown)?;
+        handle.join().unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_control_commands() -> TestResult {
+        let temp_dir = tempfile::tempdir()?;
+        let temp_dir_path = temp_dir.path().to_owned();
+
+        let (control_tx, control_rx) = mpsc::channel();
+        let channel = WatchChannel::new();
+        channel.forward_control(control_rx)?;
+        let (changes_tx, changes_rx) = mpsc::channel();
+        let watcher = FileWatcher::new(&AppConfig::default());
+
+        let handle = thread::spawn(move || {
+            watcher.watch(
+                &temp_dir_path,
+                |paths| {
+                    changes_tx.send(paths.clone())?;
+                    Ok(None)
+                },
+                |_| false,
+                &channel,
+            )
+        });
+        thread::sleep(Duration::from_millis(100));
+
+        // changes are held back while paused
+        control_tx.send(ControlCommand::TogglePause)?;
+        thread::sleep(Duration::from_millis(100));
+        fs::write(temp_dir.path().join("a.txt"), "a")?;
+        assert!(changes_rx.recv_timeout(Duration::from_secs(1)).is_err());
+
+        // a requested commit ignores the pause
+        control_tx.send(ControlCommand::Commit)?;
+        let paths = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(paths.contains(&temp_dir.path().join("a.txt")));
+
+        fs::write(temp_dir.path().join("b.txt"), "b")?;
+        thread::sleep(Duration::from_millis(300));
+
+        // resuming commits the held changes
+        control_tx.send(ControlCommand::TogglePause)?;
+        let paths = changes_rx.recv_timeout(Duration::from_secs(5))?;
+        assert!(paths.contains(&temp_dir.path().join("b.txt")));
+
+        control_tx.send(ControlCommand::Reload)?;
+        assert_eq!(handle.join().unwrap()?, StopReason::Reload);
+        Ok(())
+    }
+
     #[test]
     fn test_all_paths_ignored() -> Result<()> {
-        let was_called = AtomicBool::new(false);
-        let watcher = FileWatcher::new(0, 0);
+        let watcher = FileWatcher::new(&AppConfig {
+            debounce_seconds: 0,
+            retries: 0,
+            ..AppConfig::default()
+        });
 
-        let events = vec![
-            DebouncedEvent::new(
-                Event::new(EventKind::Any).add_path(PathBuf::from("test1.txt")),
-                Instant::now(),
-            ),
-            DebouncedEvent::new(
-                Event::new(EventKind::Any).add_path(PathBuf::from("test1.txt")),
-                Instant::now(),
-            ),
-            DebouncedEvent::new(
-                Event::new(EventKind::Any).add_path(PathBuf::from("test1.txt")),
-                Instant::now(),
-            ),
-        ];
+        let event = Event::new(EventKind::Any)
+            .add_path(PathBuf::from("test1.txt"))
+            .add_path(PathBuf::from("test2.txt"));
 
-        watcher.handle_events(
-            events,
-            |_| {
-                was_called.store(true, Ordering::SeqCst);
-                Ok(())
-            },
-            |_| true,
-        )?;
+        assert!(watcher.collect_paths(event, |_| true).is_empty());
 
-        assert!(!was_called.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_file_events_skipped() -> Result<()> {
+        let log_path = PathBuf::from("/repo/.git/gitwatch.log");
+        let watcher = FileWatcher::new(&AppConfig {
+            log_file: Some(LogFile {
+                level: LogLevel::Trace,
+                max_size_mb: 10,
+                path: log_path.clone(),
+                retention: 1,
+                rotation: LogRotation::Daily,
+            }),
+            ..AppConfig::default()
+        });
 
+        let log_event = Event::new(EventKind::Any).add_path(log_path.clone());
+        assert!(watcher.collect_paths(log_event, |_| false).is_empty());
+        // renaming the rotated files
+        let rotation_event = Event::new(EventKind::Any)
+            .add_path(PathBuf::from("/repo/.git/gitwatch.log.2025-01-31"))
+            .add_path(log_path.clone());
+        assert!(watcher.collect_paths(rotation_event, |_| false).is_empty());
+
+        let event = Event::new(EventKind::Any).add_path(PathBuf::from("/repo/test.txt"));
+        let paths = watcher.collect_paths(event, |_| false);
+        assert_eq!(paths, vec![PathBuf::from("/repo/test.txt")]);
         Ok(())
     }
 }
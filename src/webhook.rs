@@ -0,0 +1,164 @@
+use std::{collections::BTreeMap, thread, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::events::EventKind;
+
+// the delay grows with each attempt
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// HTTP endpoint that receives events as JSON via POST
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Webhook {
+    /// Events to send, all if empty
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    // e.g. an Authorization header, which must not show up in `gitwatch status`
+    #[serde(default, skip_serializing)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    #[serde(default = "default_timeout_seconds")]
+    pub timeout_seconds: u64,
+    pub url: String,
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            events: Vec::new(),
+            headers: BTreeMap::new(),
+            retries: default_retries(),
+            timeout_seconds: default_timeout_seconds(),
+            url,
+        }
+    }
+
+    pub fn accepts(&self, event_kind: EventKind) -> bool {
+        self.events.is_empty() || self.events.contains(&event_kind)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            bail!("Invalid webhook URL '{}', it must start with http(s)://", self.url);
+        }
+        if self.timeout_seconds == 0 {
+            bail!("Webhook timeout must be at least 1 second");
+        }
+        Ok(())
+    }
+
+    /// Posts `payload`, retrying failed deliveries.
+    /// Blocks until it is delivered or all attempts failed.
+    pub fn deliver(&self, payload: &serde_json::Value) -> Result<()> {
+        let agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(self.timeout_seconds)))
+            .build()
+            .new_agent();
+        let body = payload.to_string();
+
+        let mut attempt = 0;
+        loop {
+            let mut request = agent
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .header(
+                    "User-Agent",
+                    concat!("gitwatch/", env!("CARGO_PKG_VERSION")),
+                );
+            for (name, value) in &self.headers {
+                request = request.header(name, value);
+            }
+
+            match request.send(&body) {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    debug!(
+                        "Failed to deliver webhook to {}: {e}. Retrying... ({attempt}/{})",
+                        self.url, self.retries
+                    );
+                    thread::sleep(RETRY_DELAY * attempt);
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Failed to deliver webhook to {} after {} attempts",
+                            self.url,
+                            attempt + 1
+                        )
+                    })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use testresult::TestResult;
+
+    use super::*;
+    use crate::test_support::http_stub::HttpStub;
+
+    #[test]
+    fn test_deliver() -> TestResult {
+        let stub = HttpStub::start(&[500])?;
+        let mut webhook = Webhook::new(stub.url.clone());
+        webhook
+            .headers
+            .insert("Authorization".to_string(), "Bearer secret".to_string());
+
+        let payload = serde_json::json!({"event": "commit"});
+        webhook.deliver(&payload)?;
+
+        // the first attempt is retried
+        for _ in 0..2 {
+            let request = stub.next_request()?;
+            assert_eq!(request.method, "POST");
+            assert_eq!(request.path, "/hook");
+            assert_eq!(request.header("content-type"), Some("application/json"));
+            assert_eq!(request.header("authorization"), Some("Bearer secret"));
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&request.body)?,
+                payload
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deliver_failure() -> TestResult {
+        let stub = HttpStub::start(&[500, 502])?;
+        let webhook = Webhook {
+            retries: 1,
+            ..Webhook::new(stub.url.clone())
+        };
+        let err = webhook.deliver(&serde_json::json!({})).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Failed to deliver webhook to {} after 2 attempts", stub.url)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Webhook::new("https://example.com/hook".to_string())
+            .validate()
+            .is_ok());
+        assert!(Webhook::new("example.com/hook".to_string())
+            .validate()
+            .is_err());
+    }
+}
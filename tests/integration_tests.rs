@@ -1,10 +1,30 @@
-use std::{sync::Once, thread, time::Duration};
+use std::{
+    fs,
+    os::unix::net::UnixDatagram,
+    path::PathBuf,
+    process::{self, Stdio},
+    sync::{Arc, Mutex, Once},
+    thread,
+    time::Duration,
+};
 
 use assert_cmd::Command;
-use gitwatch_rs::{app::App, app_config::AppConfig, cli::LogLevel, logger::setup_logger};
+use clap::Parser;
+use gitwatch_rs::{
+    app::App,
+    app_config::AppConfig,
+    builder::AppBuilder,
+    cli::{CliOptions, LogLevel},
+    commit_message::{ChangeSet, CommitMessageProvider},
+    control::ControlCommand,
+    logger::setup_logger,
+    metrics::MetricsServer,
+    observer::GitwatchObserver,
+    watcher::{CommitMode, WatcherBackend},
+};
 use regex::Regex;
 use support::{
-    AppRunner, TestRepo, IGNORED_FILE_NAME, TEST_COMMIT_MESSAGE, TEST_FILE_CONTENT, TEST_FILE_NAME,
+    AppRunner, HttpStub, TestRepo, IGNORED_FILE_NAME, TEST_COMMIT_MESSAGE, TEST_FILE_CONTENT, TEST_FILE_NAME,
     TEST_GENERATED_COMMIT_MESSAGE, TEST_REMOTE,
 };
 use testresult::TestResult;
@@ -26,7 +46,7 @@ fn test_commit_on_start() -> TestResult {
     let test_repo = TestRepo::new()?;
 
     let config = test_repo.default_app_config();
-    let app = App::new(config)?;
+    let mut app = App::new(config)?;
 
     test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
 
@@ -43,6 +63,8 @@ fn test_watch() -> TestResult {
     let test_repo = TestRepo::new()?;
     let config = AppConfig {
         watch: true,
+        // creating & writing the file must not end up in separate commits under load
+        debounce_seconds: 1,
         ..test_repo.default_app_config()
     };
     let app = App::new(config)?;
@@ -58,6 +80,49 @@ fn test_watch() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_commit_interval() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        commit_mode: CommitMode::Interval,
+        ..test_repo.default_app_config()
+    };
+    let app = App::new(config)?;
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
+#[test]
+fn test_watch_poll_backend() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        watcher_backend: WatcherBackend::Poll,
+        ..test_repo.default_app_config()
+    };
+    let app = App::new(config)?;
+    let runner = AppRunner::run(app);
+
+    // give the poll watcher time for its initial scan
+    thread::sleep(Duration::from_millis(500));
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
 #[test]
 fn test_commit_message_script() -> TestResult {
     setup();
@@ -69,7 +134,7 @@ fn test_commit_message_script() -> TestResult {
         commit_message_script: Some(test_repo.dir.path().join(commit_message_script)),
         ..test_repo.default_app_config()
     };
-    let app = App::new(config)?;
+    let mut app = App::new(config)?;
 
     test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
 
@@ -105,6 +170,67 @@ fn test_debounce() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_pause_during_git_operation() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        commit_on_start: false,
+        ..test_repo.default_app_config()
+    };
+    let app = App::new(config)?;
+
+    // simulate an in-progress merge
+    let head_id = test_repo.repo.head()?.peel_to_commit()?.id();
+    let merge_head_path = test_repo.repo.path().join("MERGE_HEAD");
+    fs::write(&merge_head_path, format!("{head_id}\n"))?;
+
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    thread::sleep(Duration::from_millis(500));
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 0)?;
+
+    // finishing the merge resumes autocommits
+    fs::remove_file(&merge_head_path)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
+#[test]
+fn test_resume_after_index_lock() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        commit_on_start: false,
+        ..test_repo.default_app_config()
+    };
+    let app = App::new(config)?;
+
+    // simulate another git process holding the index, e.g. an editor's `git status`
+    let index_lock_path = test_repo.repo.path().join("index.lock");
+    fs::write(&index_lock_path, "")?;
+
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    thread::sleep(Duration::from_millis(500));
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 0)?;
+
+    // releasing the lock resumes autocommits without further changes
+    fs::remove_file(&index_lock_path)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
 #[test]
 fn test_gitignore() -> TestResult {
     setup();
@@ -155,7 +281,7 @@ fn test_dry_run() -> TestResult {
         dry_run: true,
         ..test_repo.default_app_config()
     };
-    let app = App::new(config)?;
+    let mut app = App::new(config)?;
 
     test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
     app.run(None)?;
@@ -183,6 +309,315 @@ fn test_main_valid_args() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_json_log_format() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--watch=false")
+        .arg("--log-format=json");
+    let output = cmd.assert().success().get_output().clone();
+    assert!(output.stdout.is_empty());
+
+    let records = String::from_utf8(output.stderr)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let commit_record = records
+        .iter()
+        .find(|record| record["event"] == "commit")
+        .ok_or("no commit record")?;
+    assert_eq!(commit_record["level"], "info");
+    assert_eq!(commit_record["files"], 1);
+    let head_id = test_repo.repo.head()?.peel_to_commit()?.id().to_string();
+    assert_eq!(commit_record["commit_id"], head_id.as_str());
+    assert_eq!(
+        commit_record["repo"],
+        test_repo.dir.path().canonicalize()?.to_str().unwrap()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_log_file() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--watch=false")
+        .arg("--log-file=.git/gitwatch.log")
+        .arg("--log-file-level=debug");
+    let output = cmd.assert().success().get_output().clone();
+
+    // the console keeps the info level
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Created commit"));
+    let log = fs::read_to_string(test_repo.dir.path().join(".git/gitwatch.log"))?;
+    assert!(log.contains(" INFO  Created commit"), "Unexpected log: {log}");
+    assert!(log.contains(" DEBUG "), "Unexpected log: {log}");
+    assert!(!String::from_utf8(output.stderr)?.contains("[debug]"));
+
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+    Ok(())
+}
+
+#[test]
+fn test_event_hooks() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    let output_dir = tempfile::tempdir()?;
+    let output_path = output_dir.path().join("on_commit");
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--watch=false")
+        .arg("--on-commit")
+        .arg(format!(
+            "(echo \"$GITWATCH_EVENT $GITWATCH_FILES\"; cat) > {}",
+            output_path.display()
+        ));
+    cmd.assert().success();
+
+    // gitwatch waits for the hook before exiting
+    let output = fs::read_to_string(&output_path)?;
+    let (env_line, payload) = output.split_once('\n').ok_or("no payload")?;
+    assert_eq!(env_line, format!("commit {TEST_FILE_NAME}"));
+    let payload: serde_json::Value = serde_json::from_str(payload)?;
+    let head_id = test_repo.repo.head()?.peel_to_commit()?.id().to_string();
+    assert_eq!(payload["commit_id"], head_id.as_str());
+    assert_eq!(payload["message"], TEST_COMMIT_MESSAGE);
+    assert_eq!(payload["files"], serde_json::json!([TEST_FILE_NAME]));
+    Ok(())
+}
+
+#[test]
+fn test_webhook() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    // the first delivery fails & is retried
+    let stub = HttpStub::start(&[503])?;
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--watch=false")
+        .arg("--webhook")
+        .arg(&stub.url);
+    cmd.assert().success();
+
+    let head = test_repo.repo.head()?;
+    let branch = head.shorthand()?;
+    let commit_id = head.peel_to_commit()?.id().to_string();
+    let repository = test_repo.dir.path().canonicalize()?;
+    let expected_payload = serde_json::json!({
+        "event": "commit",
+        "branch": branch,
+        "commit_id": commit_id,
+        "files": [TEST_FILE_NAME],
+        "message": TEST_COMMIT_MESSAGE,
+        "repository": repository,
+    });
+    for _ in 0..2 {
+        let request = stub.next_request()?;
+        assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/hook"));
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        let payload: serde_json::Value = serde_json::from_str(&request.body)?;
+        assert_eq!(payload, expected_payload);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_metrics() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        ..test_repo.default_app_config()
+    };
+    let app = App::new(config)?;
+    let server = MetricsServer::bind("127.0.0.1:0".parse()?, app.state())?;
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(IGNORED_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+    // the server outlives the app
+    runner.shutdown()?;
+
+    let metrics = ureq::get(format!("http://{}/metrics", server.address))
+        .call()?
+        .body_mut()
+        .read_to_string()?;
+    let label = format!(
+        "{{repo=\"{}\"}}",
+        test_repo.dir.path().canonicalize()?.display()
+    );
+    for expected_line in [
+        format!("gitwatch_commits_total{label} 1"),
+        format!("gitwatch_pending_changes{label} 0"),
+        format!("gitwatch_push_failures_total{label} 0"),
+        format!("gitwatch_watcher_errors_total{label} 0"),
+    ] {
+        assert!(
+            metrics.lines().any(|line| line == expected_line),
+            "Missing '{expected_line}' in:\n{metrics}"
+        );
+    }
+    let ignored_events = metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(&format!("gitwatch_ignored_events_total{label} ")))
+        .map(str::parse::<u64>)
+        .transpose()?;
+    assert!(ignored_events.is_some_and(|count| count > 0));
+    Ok(())
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<String>>,
+}
+
+impl GitwatchObserver for RecordingObserver {
+    fn on_changes_detected(&self, paths: &[PathBuf]) {
+        let file_names = paths
+            .iter()
+            .filter_map(|path| path.file_name())
+            .map(|name| name.to_string_lossy())
+            .collect::<Vec<_>>();
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("changes_detected {}", file_names.join(",")));
+    }
+
+    fn on_commit(&self, _commit_id: &str, files: &[String], message: &str) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("commit {} {message}", files.join(",")));
+    }
+}
+
+#[test]
+fn test_builder_observer() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let observer = Arc::new(RecordingObserver::default());
+    let app = AppBuilder::new(test_repo.dir.path())
+        .with_commit_message(TEST_COMMIT_MESSAGE)
+        .with_debounce_seconds(1)
+        .with_observer(observer.clone())
+        .build()?;
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+    runner.shutdown()?;
+
+    let events = observer.events.lock().unwrap();
+    assert!(events
+        .iter()
+        .all(|event| event.starts_with("changes_detected") || event.starts_with("commit")));
+    assert!(events.contains(&format!("changes_detected {TEST_FILE_NAME}")));
+    assert_eq!(
+        events.last(),
+        Some(&format!("commit {TEST_FILE_NAME} {TEST_COMMIT_MESSAGE}"))
+    );
+    Ok(())
+}
+
+struct ChangedFilesMessage;
+
+impl CommitMessageProvider for ChangedFilesMessage {
+    fn commit_message(&self, changes: &ChangeSet) -> anyhow::Result<String> {
+        let diff = changes.diff()?;
+        Ok(format!(
+            "Update {} ({} lines)",
+            changes.paths().join(", "),
+            diff.lines().filter(|line| line.starts_with('+')).count()
+        ))
+    }
+}
+
+#[test]
+fn test_commit_message_provider() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    let mut app = AppBuilder::new(test_repo.dir.path())
+        .with_commit_message_provider(Arc::new(ChangedFilesMessage))
+        .with_watch(false)
+        .build()?;
+    app.run(None)?;
+
+    // the file content and the "+++" header
+    test_repo.verify_commits(&format!("Update {TEST_FILE_NAME} (2 lines)"), 1)?;
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_async() -> TestResult {
+    use gitwatch_rs::async_api::{event_channel, AppEvent};
+    use tokio_stream::StreamExt;
+    use tokio_util::sync::CancellationToken;
+
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let (observer, mut events) = event_channel();
+    let app = AppBuilder::new(test_repo.dir.path())
+        .with_commit_message(TEST_COMMIT_MESSAGE)
+        .with_debounce_seconds(1)
+        .with_observer(observer)
+        .build_async()
+        .await?;
+    let cancellation = CancellationToken::new();
+    let handle = tokio::spawn(app.run_async(cancellation.clone()));
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    let commit = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(event) = events.next().await {
+            if let AppEvent::Commit { files, message, .. } = event {
+                return Some((files, message));
+            }
+        }
+        None
+    })
+    .await?;
+    assert_eq!(
+        commit,
+        Some((
+            vec![TEST_FILE_NAME.to_string()],
+            TEST_COMMIT_MESSAGE.to_string()
+        ))
+    );
+
+    cancellation.cancel();
+    tokio::time::timeout(Duration::from_secs(5), handle).await???;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+    Ok(())
+}
+
 #[test]
 fn test_main_invalid_path() -> TestResult {
     let mut cmd = Command::cargo_bin("gitwatch")?;
@@ -204,7 +639,7 @@ fn test_push() -> TestResult {
         remote: Some(TEST_REMOTE.to_string()),
         ..test_repo.default_app_config()
     };
-    let app = App::new(config)?;
+    let mut app = App::new(config)?;
 
     test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
     app.run(None)?;
@@ -231,7 +666,7 @@ fn test_push_invalid_remote() -> TestResult {
         ..test_repo.default_app_config()
     };
 
-    let app = App::new(config)?;
+    let mut app = App::new(config)?;
 
     test_repo.repo.remote_delete(TEST_REMOTE)?;
 
@@ -248,6 +683,464 @@ fn test_push_invalid_remote() -> TestResult {
     Ok(())
 }
 
+#[test]
+fn test_reload_config() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    let config = AppConfig {
+        watch: true,
+        // creating & writing the file must not end up in separate commits under load
+        debounce_seconds: 1,
+        ..test_repo.default_app_config()
+    };
+    let reloaded_config = AppConfig {
+        commit_message: Some(TEST_GENERATED_COMMIT_MESSAGE.to_string()),
+        ..config.clone()
+    };
+    let app = App::new(config)?.with_config_loader(move || Ok(reloaded_config.clone()));
+    let runner = AppRunner::run(app);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    runner.send(ControlCommand::Reload)?;
+    thread::sleep(Duration::from_millis(300));
+
+    test_repo.write_file(TEST_FILE_NAME, "changed content")?;
+    test_repo.verify_commits(TEST_GENERATED_COMMIT_MESSAGE, 1)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
+#[test]
+fn test_reload_invalid_config_file() -> TestResult {
+    setup();
+
+    let test_repo = TestRepo::new()?;
+    // the config file takes precedence over the cli flags
+    test_repo.write_file(
+        "gitwatch.yaml",
+        &format!("commit_message: {TEST_GENERATED_COMMIT_MESSAGE}\n"),
+    )?;
+    let cli_opts = CliOptions::parse_from([
+        "gitwatch",
+        test_repo.dir.path().to_str().unwrap(),
+        "--commit-message",
+        TEST_COMMIT_MESSAGE,
+        "--commit-on-start=false",
+        "--debounce-seconds=1",
+    ]);
+    let config = AppConfig::new(cli_opts.clone())?;
+    let app = App::new(config)?.with_config_loader(move || AppConfig::reload(cli_opts.clone()));
+    let runner = AppRunner::run(app);
+
+    // the broken config file is committed before reloading
+    test_repo.write_file("gitwatch.yaml", "commit_message: [")?;
+    thread::sleep(Duration::from_millis(300));
+    runner.send(ControlCommand::Reload)?;
+    test_repo.verify_commits(TEST_GENERATED_COMMIT_MESSAGE, 1)?;
+
+    // the running configuration is kept instead of falling back to the cli flags
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    test_repo.verify_commits(TEST_GENERATED_COMMIT_MESSAGE, 2)?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 0)?;
+
+    runner.shutdown()?;
+    Ok(())
+}
+
+#[test]
+fn test_signals() -> TestResult {
+    let test_repo = TestRepo::new()?;
+
+    // a long debounce time, so only signals trigger commits
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--commit-on-start=false")
+        .arg("--debounce-seconds=600")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let kill = |signal: &str| {
+        process::Command::new("kill")
+            .args([signal, &child.id().to_string()])
+            .status()
+    };
+    thread::sleep(Duration::from_millis(500));
+
+    // SIGUSR1 commits immediately
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    kill("-USR1")?;
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+
+    // SIGTERM commits remaining changes before exiting
+    test_repo.write_file(TEST_FILE_NAME, "changed content")?;
+    kill("-TERM")?;
+    assert!(child.wait()?.success());
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 2)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_manual_pause_hook() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let output_dir = tempfile::tempdir()?;
+    let output_path = output_dir.path().join("on_pause");
+
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--on-pause")
+        .arg(format!(
+            "echo \"$GITWATCH_EVENT $GITWATCH_REASON\" >> {}",
+            output_path.display()
+        ))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let kill = |signal: &str| {
+        process::Command::new("kill")
+            .args([signal, &child.id().to_string()])
+            .status()
+    };
+    thread::sleep(Duration::from_millis(500));
+
+    // pausing runs the hook, resuming doesn't
+    kill("-USR2")?;
+    thread::sleep(Duration::from_millis(500));
+    kill("-USR2")?;
+    thread::sleep(Duration::from_millis(500));
+    kill("-TERM")?;
+    assert!(child.wait()?.success());
+
+    assert_eq!(
+        fs::read_to_string(&output_path)?,
+        "pause paused manually\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ctl() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let runtime_dir = tempfile::tempdir()?;
+
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--commit-on-start=false")
+        .arg("--debounce-seconds=600")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(500));
+
+    let ctl = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("gitwatch").unwrap();
+        cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+            .arg("ctl")
+            .arg(test_repo.dir.path())
+            .args(args);
+        cmd.assert().success()
+    };
+
+    ctl(&["pause"]);
+    ctl(&["status"]).stdout(predicates::str::contains(r#""paused": true"#));
+    ctl(&["resume"]);
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    ctl(&["set-message", "one-off message"]);
+    ctl(&["commit"]);
+    test_repo.verify_commits("one-off message", 1)?;
+
+    process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()?;
+    assert!(child.wait()?.success());
+
+    // the socket is removed on exit
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .arg("ctl")
+        .arg(test_repo.dir.path())
+        .arg("status");
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("is gitwatch running"));
+
+    Ok(())
+}
+
+#[test]
+fn test_status() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let runtime_dir = tempfile::tempdir()?;
+
+    let status = || -> TestResult<serde_json::Value> {
+        let mut cmd = Command::cargo_bin("gitwatch")?;
+        cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+            .arg("status")
+            .arg(test_repo.dir.path())
+            .arg("--json");
+        let output = cmd.assert().success().get_output().stdout.clone();
+        Ok(serde_json::from_slice(&output)?)
+    };
+
+    test_repo.write_file(TEST_FILE_NAME, TEST_FILE_CONTENT)?;
+    let report = status()?;
+    assert_eq!(report["running"], false);
+    assert_eq!(report["pending_changes"], 1);
+
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--remote")
+        .arg(TEST_REMOTE)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // commits & pushes the pending change on start
+    test_repo.verify_commits(TEST_COMMIT_MESSAGE, 1)?;
+    thread::sleep(Duration::from_millis(500));
+
+    let report = status()?;
+    assert_eq!(report["running"], true);
+    assert_eq!(report["pending_changes"], 0);
+    assert_eq!(report["ahead"], 0);
+    assert_eq!(report["instance"]["pid"], child.id());
+    assert!(report["instance"]["last_commit"].is_u64());
+    assert!(report["instance"]["last_push"].is_u64());
+    assert_eq!(report["instance"]["config"]["remote"], TEST_REMOTE);
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .arg("status")
+        .arg(test_repo.dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicates::str::contains("gitwatch is running"))
+        .stdout(predicates::str::contains("Unpushed commits: 0"));
+
+    process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()?;
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_fsmonitor() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let runtime_dir = tempfile::tempdir()?;
+    let fsmonitor = |token: &str| {
+        let mut cmd = Command::cargo_bin("gitwatch").unwrap();
+        cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+            .current_dir(test_repo.dir.path())
+            .args(["fsmonitor", "2", token]);
+        cmd.assert()
+    };
+
+    // git scans the working tree itself while gitwatch isn't running
+    fsmonitor("").failure();
+
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .arg("--commit-on-start=false")
+        .arg("--debounce-seconds=600")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(500));
+
+    // an unknown token gets a trivial response
+    let output = fsmonitor("").success().get_output().stdout.clone();
+    let output = String::from_utf8(output)?;
+    let (token, paths) = output.split_once('\0').unwrap();
+    assert!(token.starts_with("gitwatch:"));
+    assert_eq!(paths, "/\0");
+
+    test_repo.write_file("new-file.txt", TEST_FILE_CONTENT)?;
+    fsmonitor(token)
+        .success()
+        .stdout(predicates::str::contains("\0new-file.txt\0"));
+
+    let mut cmd = Command::cargo_bin("gitwatch")?;
+    cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .current_dir(test_repo.dir.path())
+        .args(["fsmonitor", "1", token]);
+    cmd.assert()
+        .failure()
+        .stderr(predicates::str::contains("Unsupported fsmonitor hook version"));
+
+    // git itself uses the hook
+    let hook = format!("'{}' fsmonitor", env!("CARGO_BIN_EXE_gitwatch"));
+    let output = process::Command::new("git")
+        .env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .current_dir(test_repo.dir.path())
+        .args(["-c", &format!("core.fsmonitor={hook}"), "status", "--porcelain"])
+        .output()?;
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)?.contains("?? new-file.txt"));
+
+    process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()?;
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_single_instance() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let runtime_dir = tempfile::tempdir()?;
+
+    let watch = |extra_args: &[&str]| {
+        let mut cmd = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"));
+        cmd.env("XDG_RUNTIME_DIR", runtime_dir.path())
+            .arg("watch")
+            .arg(test_repo.dir.path())
+            .arg("--commit-message")
+            .arg(TEST_COMMIT_MESSAGE)
+            .args(extra_args);
+        cmd
+    };
+
+    let mut first = watch(&[])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::sleep(Duration::from_millis(500));
+
+    let output = watch(&[]).output()?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "gitwatch is already running for this repository (PID {}",
+            first.id()
+        )),
+        "Unexpected error: {stderr}"
+    );
+
+    let mut second = watch(&["--replace"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    assert!(first.wait()?.success());
+    thread::sleep(Duration::from_millis(500));
+    let lock_file = fs::read_to_string(test_repo.repo.path().join("gitwatch.lock"))?;
+    assert!(lock_file.starts_with(&format!("{}\n", second.id())));
+
+    process::Command::new("kill")
+        .args(["-TERM", &second.id().to_string()])
+        .status()?;
+    assert!(second.wait()?.success());
+    assert!(!test_repo.repo.path().join("gitwatch.lock").exists());
+    Ok(())
+}
+
+#[test]
+fn test_sd_notify() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let runtime_dir = tempfile::tempdir()?;
+    let notify_socket_path = runtime_dir.path().join("notify.sock");
+    let notify_socket = UnixDatagram::bind(&notify_socket_path)?;
+    notify_socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut buffer = [0; 64];
+    let mut receive = || -> TestResult<String> {
+        let len = notify_socket.recv(&mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer[..len]).to_string())
+    };
+
+    let mut child = process::Command::new(env!("CARGO_BIN_EXE_gitwatch"))
+        .env("XDG_RUNTIME_DIR", runtime_dir.path())
+        .env("NOTIFY_SOCKET", &notify_socket_path)
+        .env("WATCHDOG_USEC", "60000000")
+        .arg("watch")
+        .arg(test_repo.dir.path())
+        .arg("--commit-message")
+        .arg(TEST_COMMIT_MESSAGE)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    assert_eq!(receive()?, "READY=1");
+    assert_eq!(receive()?, "WATCHDOG=1");
+
+    process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()?;
+    assert_eq!(receive()?, "STOPPING=1");
+    assert!(child.wait()?.success());
+    Ok(())
+}
+
+#[test]
+fn test_service_install() -> TestResult {
+    let test_repo = TestRepo::new()?;
+    let config_dir = tempfile::tempdir()?;
+    let repo_path = test_repo.dir.path().canonicalize()?;
+
+    let service = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("gitwatch").unwrap();
+        cmd.env("XDG_CONFIG_HOME", config_dir.path())
+            .arg("service")
+            .args(args);
+        cmd
+    };
+
+    service(&["install", repo_path.to_str().unwrap(), "--debounce-seconds=5"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Either commit-message or commit-message-script must be set",
+        ));
+
+    service(&[
+        "install",
+        repo_path.to_str().unwrap(),
+        "--commit-message",
+        TEST_COMMIT_MESSAGE,
+    ])
+    .assert()
+    .success();
+    let unit_dir = config_dir.path().join("systemd/user");
+    let unit_files = fs::read_dir(&unit_dir)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(unit_files.len(), 1);
+    let unit_name = unit_files[0].file_name();
+    assert!(unit_name.to_string_lossy().starts_with("gitwatch@"));
+    let unit = fs::read_to_string(unit_files[0].path())?;
+    assert!(unit.contains(&format!(
+        "watch {} --commit-message \"{TEST_COMMIT_MESSAGE}\"\n",
+        repo_path.display()
+    )));
+
+    // systemctl may not be available, but the unit is removed anyway
+    service(&["uninstall", repo_path.to_str().unwrap()])
+        .assert()
+        .success();
+    assert!(!unit_dir.join(unit_name).exists());
+    Ok(())
+}
+
 #[test]
 fn test_completion_command() -> TestResult {
     let mut cmd = Command::cargo_bin("gitwatch")?;
@@ -1,15 +1,15 @@
 use std::{thread, time::Duration};
 
 use anyhow::{bail, Result};
-use gitwatch_rs::app::App;
+use gitwatch_rs::{app::App, control::ControlCommand};
 
 pub struct AppRunner {
     handle: thread::JoinHandle<Result<(), Box<dyn std::error::Error + Send + Sync>>>,
-    shutdown_tx: std::sync::mpsc::Sender<()>,
+    control_tx: std::sync::mpsc::Sender<ControlCommand>,
 }
 
 impl AppRunner {
-    pub fn run(app: App) -> Self {
+    pub fn run(mut app: App) -> Self {
         let (tx, rx) = std::sync::mpsc::channel();
         let handle = thread::spawn(move || {
             app.run(Some(rx))?;
@@ -20,12 +20,19 @@ impl AppRunner {
 
         Self {
             handle,
-            shutdown_tx: tx,
+            control_tx: tx,
         }
     }
 
+    pub fn send(&self, command: ControlCommand) -> Result<()> {
+        self.control_tx.send(command)?;
+        Ok(())
+    }
+
     pub fn shutdown(self) -> Result<()> {
-        self.shutdown_tx.send(()).unwrap_or_default();
+        self.control_tx
+            .send(ControlCommand::Shutdown)
+            .unwrap_or_default();
         if let Err(e) = self.handle.join().expect("Thread panicked") {
             bail!(e);
         }
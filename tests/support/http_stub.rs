@@ -0,0 +1,107 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+/// Request received by an `HttpStub`
+#[derive(Debug)]
+pub struct StubRequest {
+    pub body: String,
+    // header names are lowercase
+    pub headers: Vec<(String, String)>,
+    pub method: String,
+    pub path: String,
+}
+
+impl StubRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Minimal HTTP server on localhost that records the requests it receives
+pub struct HttpStub {
+    pub url: String,
+    requests: Receiver<StubRequest>,
+}
+
+impl HttpStub {
+    /// Responds with `statuses` in order, then with 200
+    pub fn start(statuses: &[u16]) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/hook", listener.local_addr()?);
+        let (tx, rx) = mpsc::channel();
+        let statuses = statuses.to_vec();
+
+        thread::spawn(move || {
+            let mut statuses = statuses.into_iter();
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let Ok(request) = read_request(&mut stream) else {
+                    continue;
+                };
+                let status = statuses.next().unwrap_or(200);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status} Stub\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                if tx.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+        Ok(Self { url, requests: rx })
+    }
+
+    pub fn next_request(&self) -> Result<StubRequest> {
+        self.requests
+            .recv_timeout(Duration::from_secs(10))
+            .context("No request received")
+    }
+}
+
+fn read_request(stream: &mut impl Read) -> Result<StubRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("Missing method")?.to_string();
+    let path = parts.next().context("Missing path")?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    let content_length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(StubRequest {
+        body: String::from_utf8(body)?,
+        headers,
+        method,
+        path,
+    })
+}
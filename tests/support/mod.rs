@@ -1,7 +1,9 @@
 mod app_runner;
 mod constants;
+mod http_stub;
 mod test_repo;
 
 pub use app_runner::*;
 pub use constants::*;
+pub use http_stub::*;
 pub use test_repo::*;
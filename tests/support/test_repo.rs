@@ -11,7 +11,13 @@ use std::io::Write;
 use anyhow::Result;
 use git2::Repository;
 
-use gitwatch_rs::app_config::AppConfig;
+use gitwatch_rs::{
+    app_config::AppConfig,
+    branch::BranchPolicy,
+    events::EventHooks,
+    stage::StagePolicy,
+    watcher::{CommitMode, WatcherBackend},
+};
 use tempfile::TempDir;
 
 use super::{IGNORED_FILE_NAME, TEST_COMMIT_MESSAGE, TEST_FILE_NAME};
@@ -45,15 +51,30 @@ impl TestRepo {
     pub fn default_app_config(&self) -> AppConfig {
         AppConfig {
             repository: self.dir.path().to_path_buf(),
+            branch_policy: BranchPolicy::default(),
+            commit_interval_seconds: 1,
             commit_message: Some(TEST_COMMIT_MESSAGE.to_string()),
             commit_message_script: None,
+            commit_mode: CommitMode::Debounce,
             debounce_seconds: 0,
             ignore_regex: None,
             dry_run: false,
+            event_hooks: EventHooks::default(),
+            log_file: None,
+            max_commits_per_hour: None,
+            metrics_addr: None,
+            min_commit_interval_seconds: 0,
+            poll_interval_seconds: 1,
+            reconcile_interval_seconds: 600,
             retries: 0,
+            run_hooks: false,
+            stage_policy: StagePolicy::default(),
             commit_on_start: true,
             watch: false,
+            watcher_backend: WatcherBackend::Native,
+            webhooks: Vec::new(),
             remote: None,
+            replace: false,
         }
     }
 